@@ -0,0 +1,118 @@
+/*
+    Sound as samples, for frontends to play at their own sample rate.
+    While the sound timer is above zero the buzzer plays a square wave. XO-CHIP programs can
+    load a 128-bit pattern instead, played one bit per step at 4000 * 2^((pitch - 64) / 48)
+    steps per second, over and over.
+    The samples only depend on the emulation and the samples made before, so the same program
+    and input always sound the same.
+*/
+use crate::chip::Emulation;
+
+pub const BUZZER_FREQUENCY: f64 = 440.0;
+pub const DEFAULT_VOLUME: f32 = 0.25;
+
+pub struct Audio {
+    sample_rate: f64,
+    volume: f32,
+    // position in the square wave or pattern, in cycles or pattern bits
+    phase: f64,
+}
+
+impl Audio {
+    pub fn new(sample_rate: u32) -> Self {
+        Audio { sample_rate: sample_rate.max(1) as f64, volume: DEFAULT_VOLUME, phase: 0.0 }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate as u32
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    // fills the buffer with the sound of the emulation as it is now, usually a frame's worth
+    pub fn fill(&mut self, emu: &Emulation, samples: &mut [f32]) {
+        if emu.sound_timer == 0 {
+            // the next sound starts at the beginning of its wave
+            self.phase = 0.0;
+            samples.fill(0.0);
+            return;
+        }
+        if emu.platform.is_xochip() && emu.pattern_loaded {
+            let rate = 4000.0 * 2f64.powf((emu.pitch as f64 - 64.0) / 48.0) / self.sample_rate;
+            for sample in samples.iter_mut() {
+                let bit = self.phase as usize % 128;
+                let set = emu.audio_pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                *sample = if set { self.volume } else { -self.volume };
+                self.phase = (self.phase + rate) % 128.0;
+            }
+        } else {
+            let rate = BUZZER_FREQUENCY / self.sample_rate;
+            for sample in samples.iter_mut() {
+                *sample = if self.phase < 0.5 { self.volume } else { -self.volume };
+                self.phase = (self.phase + rate) % 1.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Platform;
+
+    // a sample rate that makes whole numbers of samples per cycle
+    const BUZZER_RATE: u32 = BUZZER_FREQUENCY as u32 * 64;
+
+    #[test]
+    fn silent_without_the_sound_timer() {
+        let mut audio = Audio::new(BUZZER_RATE);
+        let mut samples = [1.0; 256];
+        audio.fill(&Emulation::new(), &mut samples);
+        assert_eq!(samples, [0.0; 256]);
+    }
+
+    #[test]
+    fn buzzer_plays_a_square_wave() {
+        let mut emu = Emulation::new();
+        emu.sound_timer = 2;
+        let mut audio = Audio::new(BUZZER_RATE);
+        let mut samples = [0.0; 128];
+        audio.fill(&emu, &mut samples);
+        // 32 samples high and 32 low, twice
+        let period: Vec<f32> = [DEFAULT_VOLUME; 32].into_iter().chain([-DEFAULT_VOLUME; 32]).collect();
+        assert_eq!(samples[..64], period[..]);
+        assert_eq!(samples[64..], period[..]);
+    }
+
+    #[test]
+    fn xochip_plays_the_pattern_at_its_pitch() {
+        let mut emu = Emulation::new();
+        emu.set_platform(Platform::XoChip);
+        emu.sound_timer = 2;
+        emu.audio_pattern = [0; 16];
+        emu.audio_pattern[0] = 0xF0;
+        emu.pattern_loaded = true;
+        emu.pitch = 64;
+        // 4000 steps per second, each bit of the pattern lasts 2 samples
+        let mut audio = Audio::new(8000);
+        let mut samples = [0.0; 272];
+        audio.fill(&emu, &mut samples);
+        assert_eq!(samples[..8], [DEFAULT_VOLUME; 8]);
+        assert_eq!(samples[8..256], [-DEFAULT_VOLUME; 248]);
+        // and over again
+        assert_eq!(samples[256..264], [DEFAULT_VOLUME; 8]);
+
+        // an octave, 48 steps higher, is twice as fast
+        emu.pitch = 64 + 48;
+        let mut audio = Audio::new(8000);
+        audio.fill(&emu, &mut samples);
+        assert_eq!(samples[..4], [DEFAULT_VOLUME; 4]);
+        assert_eq!(samples[4..8], [-DEFAULT_VOLUME; 4]);
+    }
+}
@@ -0,0 +1,176 @@
+/*
+    Cheat engine.
+    A search starts with every RAM address as a candidate and a snapshot of RAM.
+    Each filter compares the current RAM to the snapshot (or to a value),
+    keeps the addresses that match and takes a new snapshot.
+    Found addresses can be frozen as cheats, which are written back to RAM every frame.
+    Cheats are stored as plain text, one per line, under a header with the ROM hash:
+
+        rom 0123456789abcdef0123456789abcdef01234567
+        0x2F3 03 on Infinite lives
+*/
+use std::error::Error;
+use std::fmt;
+
+use crate::chip;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchFilter {
+    Equal(u8),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+pub struct CheatSearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl CheatSearch {
+    // starts a new search with every address as a candidate
+    pub fn new(emu: &chip::Emulation) -> Self {
+        Self {
+            snapshot: emu.ram[..emu.memory_size()].to_vec(),
+            candidates: (0..emu.memory_size()).map(|address| address as u16).collect(),
+        }
+    }
+
+    // keeps the candidates matching the filter and takes a new snapshot
+    pub fn filter(&mut self, emu: &chip::Emulation, filter: SearchFilter) {
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|&address| {
+            let old = snapshot[address as usize];
+            let new = emu.ram[address as usize];
+            match filter {
+                SearchFilter::Equal(value) => new == value,
+                SearchFilter::Changed => new != old,
+                SearchFilter::Unchanged => new == old,
+                SearchFilter::Increased => new > old,
+                SearchFilter::Decreased => new < old,
+            }
+        });
+        let size = self.snapshot.len();
+        self.snapshot.copy_from_slice(&emu.ram[..size]);
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    // value of an address at the last snapshot
+    pub fn value(&self, address: u16) -> u8 {
+        self.snapshot[address as usize]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub address: u16,
+    pub value: u8,
+    pub enabled: bool,
+    pub name: String,
+}
+
+impl Cheat {
+    pub fn new(address: u16, value: u8, name: &str) -> Self {
+        Self { address, value, enabled: true, name: name.to_string() }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CheatError {
+    MissingHeader,
+    WrongRom(String),
+    InvalidLine(usize),
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheatError::MissingHeader => write!(f, "missing 'rom <hash>' header"),
+            CheatError::WrongRom(hash) => write!(f, "cheats are for another rom ({})", hash),
+            CheatError::InvalidLine(line) => write!(f, "invalid cheat on line {}", line),
+        }
+    }
+}
+
+impl Error for CheatError {}
+
+// writes the cheats of a rom in the cheat file format
+pub fn to_text(rom_hash: &str, cheats: &[Cheat]) -> String {
+    let mut text = format!("rom {}\n", rom_hash);
+    for cheat in cheats {
+        let state = if cheat.enabled { "on" } else { "off" };
+        let line = format!("0x{:03X} {:02X} {} {}", cheat.address, cheat.value, state, cheat.name);
+        text += line.trim_end();
+        text += "\n";
+    }
+    text
+}
+
+// parses a cheat file, checking that it belongs to the rom with the given hash
+pub fn from_text(rom_hash: &str, text: &str) -> Result<Vec<Cheat>, CheatError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    match lines.next() {
+        Some((_, header)) => match header.strip_prefix("rom ") {
+            Some(hash) if hash.trim().eq_ignore_ascii_case(rom_hash) => (),
+            Some(hash) => return Err(CheatError::WrongRom(hash.trim().to_string())),
+            None => return Err(CheatError::MissingHeader),
+        },
+        None => return Err(CheatError::MissingHeader),
+    }
+
+    lines.map(|(number, line)| parse_cheat(line).ok_or(CheatError::InvalidLine(number))).collect()
+}
+
+// a cheat line is: address value on|off [name], separated by any amount of whitespace
+fn parse_cheat(line: &str) -> Option<Cheat> {
+    let mut fields = line.split_whitespace();
+    let address = fields.next()?;
+    let address = address.strip_prefix("0x").or_else(|| address.strip_prefix("0X")).unwrap_or(address);
+    let address = u16::from_str_radix(address, 16).ok()?;
+    let value = u8::from_str_radix(fields.next()?, 16).ok()?;
+    let enabled = match fields.next()? {
+        "on" => true,
+        "off" => false,
+        _ => return None,
+    };
+    let name = fields.collect::<Vec<_>>().join(" ");
+    Some(Cheat { address, value, enabled, name })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    #[test]
+    fn parses_padded_lines() {
+        let text = format!("rom {}\n0x2F3  03\t on  Infinite  lives\n  2f4\t\t0A off\n", HASH);
+        let cheats = vec![
+            Cheat { address: 0x2F3, value: 0x03, enabled: true, name: String::from("Infinite lives") },
+            Cheat { address: 0x2F4, value: 0x0A, enabled: false, name: String::new() },
+        ];
+        assert_eq!(from_text(HASH, &text), Ok(cheats));
+    }
+
+    #[test]
+    fn rejects_incomplete_lines() {
+        let text = format!("rom {}\n0x2F3 03\n", HASH);
+        assert_eq!(from_text(HASH, &text), Err(CheatError::InvalidLine(2)));
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let cheats = vec![Cheat { address: 0x2F3, value: 3, enabled: true, name: String::from("Infinite lives") }];
+        assert_eq!(from_text(HASH, &to_text(HASH, &cheats)), Ok(cheats));
+    }
+}
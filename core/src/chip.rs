@@ -1,37 +1,86 @@
+use std::collections::BTreeSet;
+
 use crate::font;
 use crate::cpu;
+use crate::cheats::Cheat;
+use crate::database::{self, RomInfo};
+use crate::debug::{Fault, MemoryAccess, Stop, Watchpoint};
+use crate::platform::{Platform, Quirks};
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+// SUPER-CHIP and XO-CHIP high resolution mode
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
 pub const INSTRUCTION_SIZE: u16 = 2;
 
-const RAM_SIZE: usize = 4096;
-const REGISTER_NUM: usize = 16;
-const NUM_KEYS: usize = 16;
-const STACK_SIZE: usize = 16;
+// XO-CHIP can address 64kb, the other platforms only use the first 4kb
+pub(crate) const RAM_SIZE: usize = 0x10000;
+const CHIP8_RAM_SIZE: usize = 4096;
+pub(crate) const REGISTER_NUM: usize = 16;
+pub(crate) const NUM_KEYS: usize = 16;
+pub(crate) const STACK_SIZE: usize = 16;
 
 pub struct Emulation {
     pub(crate) program_counter: u16,
     pub(crate) ram: [u8; RAM_SIZE],
-    pub(crate) frame_buffer: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    // each pixel holds one bit per bitplane, only XO-CHIP uses the second plane
+    pub(crate) frame_buffer: [u8; HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT],
+    pub(crate) hires: bool,
+    pub(crate) planes: u8,
     pub(crate) registers: [u8; REGISTER_NUM],
     pub(crate) index_register: u16,
-    stack_pointer: u16,
-    stack: [u16; STACK_SIZE],
+    pub(crate) stack_pointer: u16,
+    pub(crate) stack: [u16; STACK_SIZE],
     pub(crate) keys: [bool; NUM_KEYS],
     pub(crate) delay_timer: u8,
     pub(crate) sound_timer: u8,
+    // SUPER-CHIP RPL user flags
+    pub(crate) flags: [u8; REGISTER_NUM],
+    // XO-CHIP audio
+    pub(crate) audio_pattern: [u8; 16],
+    pub(crate) pitch: u8,
+    // until a program loads a pattern, XO-CHIP beeps like the other platforms
+    pub(crate) pattern_loaded: bool,
+    pub(crate) waiting_for_vblank: bool,
+    pub(crate) halted: bool,
+    // why the program halted, unless it exited itself
+    pub(crate) fault: Option<Fault>,
+    pub(crate) platform: Platform,
+    pub(crate) quirks: Quirks,
+    pub(crate) ticks_per_frame: u32,
+    pub(crate) rng_state: u64,
+    rom: Vec<u8>,
+    rom_hash: String,
+    rom_info: Option<RomInfo>,
+    cheats: Vec<Cheat>,
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    // set when stopped at a breakpoint, so that continuing runs the instruction there
+    at_breakpoint: bool,
+}
+
+impl Default for Emulation {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // The contents of rom are copied to address 512 in ram
 const START_ADDRESS: u16 = 0x200;
+// largest rom that fits in ram after the start address
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - START_ADDRESS as usize;
+// the large font follows the small font
+pub(crate) const BIG_FONT_ADDRESS: usize = font::SET_SIZE;
 
 impl Emulation {
     pub fn new() -> Self {
         let mut new_emulation = Self {
             program_counter: START_ADDRESS,
             ram: [0; RAM_SIZE],
-            frame_buffer: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_buffer: [0; HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT],
+            hires: false,
+            planes: 1,
             registers: [0; REGISTER_NUM],
             index_register: 0,
             stack_pointer: 0,
@@ -39,17 +88,44 @@ impl Emulation {
             keys: [false; NUM_KEYS],
             delay_timer: 0,
             sound_timer: 0,
+            flags: [0; REGISTER_NUM],
+            audio_pattern: [0; 16],
+            pitch: 64,
+            pattern_loaded: false,
+            waiting_for_vblank: false,
+            halted: false,
+            fault: None,
+            platform: Platform::default(),
+            quirks: Platform::default().quirks(),
+            ticks_per_frame: Platform::default().default_tickrate(),
+            rng_state: 0,
+            rom: Vec::new(),
+            rom_hash: String::new(),
+            rom_info: None,
+            cheats: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            at_breakpoint: false,
         };
-        // loads the fontset data into unused ram space (before rom data)
-        new_emulation.ram[..font::SET_SIZE].copy_from_slice(&font::SET);
+        new_emulation.load_fonts();
+        new_emulation.set_seed(rand::random());
         new_emulation
     }
 
-    // resets the emulation to default values
+    // resets the emulation to default values, erasing the loaded rom
     pub fn reset(&mut self) {
-        self.program_counter = START_ADDRESS;
         self.ram = [0; RAM_SIZE];
-        self.frame_buffer = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.rom.clear();
+        self.soft_reset();
+    }
+
+    // resets the emulation to default values but restores the program image of the loaded rom,
+    // like pressing reset on the machine, so the program starts over
+    pub fn soft_reset(&mut self) {
+        self.program_counter = START_ADDRESS;
+        self.frame_buffer = [0; HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT];
+        self.hires = false;
+        self.planes = 1;
         self.registers = [0; REGISTER_NUM];
         self.index_register = 0;
         self.stack_pointer = 0;
@@ -57,12 +133,183 @@ impl Emulation {
         self.keys = [false; NUM_KEYS];
         self.delay_timer = 0;
         self.sound_timer = 0;
+        self.audio_pattern = [0; 16];
+        self.pitch = 64;
+        self.pattern_loaded = false;
+        self.waiting_for_vblank = false;
+        self.halted = false;
+        self.fault = None;
+        self.at_breakpoint = false;
+        self.load_fonts();
+        let start = START_ADDRESS as usize;
+        self.ram[start..start + self.rom.len()].copy_from_slice(&self.rom);
+    }
+
+    // loads the fontset data into unused ram space (before rom data)
+    fn load_fonts(&mut self) {
         self.ram[..font::SET_SIZE].copy_from_slice(&font::SET);
+        self.ram[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + font::BIG_SET_SIZE].copy_from_slice(&font::BIG_SET);
+    }
+
+    // returns a pointer to the frame buffer, display_width() pixels per row
+    // each pixel is a bitmask of the bitplanes it is set in, 0 is background
+    pub fn get_display(&self) -> &[u8] {
+        &self.frame_buffer[..self.display_width() * self.display_height()]
+    }
+
+    pub fn display_width(&self) -> usize {
+        if self.hires { HIRES_SCREEN_WIDTH } else { SCREEN_WIDTH }
+    }
+
+    pub fn display_height(&self) -> usize {
+        if self.hires { HIRES_SCREEN_HEIGHT } else { SCREEN_HEIGHT }
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    // switches platform, using its default quirks and tick rate
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.platform = platform;
+        self.quirks = platform.quirks();
+        self.ticks_per_frame = platform.default_tickrate();
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // number of tick() calls per tick_timers() call the loaded program expects
+    pub fn ticks_per_frame(&self) -> u32 {
+        self.ticks_per_frame
     }
 
-    // returns a pointer to the frame buffer
-    pub fn get_display(&self) -> &[bool] {
-        &self.frame_buffer
+    pub fn set_ticks_per_frame(&mut self, ticks: u32) {
+        self.ticks_per_frame = ticks;
+    }
+
+    // seeds the random number generator, the same seed gives the same random numbers
+    pub fn set_seed(&mut self, seed: u64) {
+        // splitmix64 spreads the seed so that small seeds work as well as large ones
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        // xorshift never leaves a zero state, so it must not start in one
+        self.rng_state = (z ^ (z >> 31)).max(1);
+    }
+
+    // next random byte from a xorshift64* generator
+    pub(crate) fn random(&mut self) -> u8 {
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        (self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    // V0-VF
+    pub fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    pub fn stack_pointer(&self) -> u16 {
+        self.stack_pointer
+    }
+
+    // return addresses of the subroutines being run, innermost last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer as usize]
+    }
+
+    // what stopped the program, if it ran into something it couldn't do
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    // whether the program stopped itself with the SUPER-CHIP EXIT instruction
+    pub fn exited(&self) -> bool {
+        self.halted && self.fault.is_none()
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    // setters for debuggers
+    pub fn set_program_counter(&mut self, address: u16) {
+        self.program_counter = address;
+    }
+
+    pub fn set_index_register(&mut self, address: u16) {
+        self.index_register = address;
+    }
+
+    pub fn set_register(&mut self, index: usize, value: u8) {
+        self.registers[index] = value;
+    }
+
+    // the stack keeps its contents, so a pointer moved back up finds the old return addresses
+    pub fn set_stack_pointer(&mut self, pointer: u16) {
+        self.stack_pointer = pointer.min(STACK_SIZE as u16);
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
+    // writes to memory at the address, wrapping around at the end of memory
+    pub fn write_memory(&mut self, address: u16, data: &[u8]) {
+        for (offset, byte) in data.iter().enumerate() {
+            self.ram[address.wrapping_add(offset as u16) as usize] = *byte;
+        }
+    }
+
+    pub fn keys(&self) -> &[bool] {
+        &self.keys
+    }
+
+    // memory of the current platform, see memory_size()
+    pub fn memory(&self) -> &[u8] {
+        &self.ram[..self.memory_size()]
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        let size = self.memory_size();
+        &mut self.ram[..size]
+    }
+
+    // SUPER-CHIP RPL user flags, which the HP48 kept when a program ended
+    pub fn user_flags(&self) -> &[u8] {
+        &self.flags
+    }
+
+    pub fn user_flags_mut(&mut self) -> &mut [u8] {
+        &mut self.flags
+    }
+
+    // addressable memory of the current platform
+    pub fn memory_size(&self) -> usize {
+        if self.platform.is_xochip() { RAM_SIZE } else { CHIP8_RAM_SIZE }
     }
 
     // sets keys in the key array
@@ -71,28 +318,183 @@ impl Emulation {
     }
 
     // load ROM data into RAM from start address
+    // ROMs found in the database switch to their platform, quirks and tick rate,
+    // others run with the defaults whatever ran before
     pub fn load(&mut self, data: &[u8]) {
         let start = START_ADDRESS as usize;
         let end = (START_ADDRESS as usize) + data.len();
         self.ram[start..end].copy_from_slice(data);
+        self.rom = data.to_vec();
+        self.rom_hash = sha1_smol::Sha1::from(data).hexdigest();
+        self.rom_info = database::lookup(&self.rom_hash);
+        self.set_platform(Platform::default());
+        if let Some(info) = &self.rom_info {
+            self.platform = info.platform;
+            self.quirks = info.quirks;
+            self.ticks_per_frame = info.tickrate;
+        }
+    }
+
+    // the loaded ROM, empty after a hard reset
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    // SHA-1 hash of the loaded ROM as lowercase hex
+    pub fn rom_hash(&self) -> &str {
+        &self.rom_hash
+    }
+
+    // database entry of the loaded ROM
+    pub fn rom_info(&self) -> Option<&RomInfo> {
+        self.rom_info.as_ref()
+    }
+
+    // cheats are frozen to their values every frame
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn cheats_mut(&mut self) -> &mut Vec<Cheat> {
+        &mut self.cheats
+    }
+
+    fn apply_cheats(&mut self) {
+        for cheat in self.cheats.iter().filter(|cheat| cheat.enabled) {
+            self.ram[cheat.address as usize] = cheat.value;
+        }
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    // adds a breakpoint at the address, or removes the one there; returns whether one is set now
+    pub fn toggle_breakpoint(&mut self, address: u16) -> bool {
+        if self.breakpoints.remove(&address) {
+            return false;
+        }
+        self.breakpoints.insert(address);
+        true
+    }
+
+    pub fn set_breakpoint(&mut self, address: u16, enabled: bool) {
+        if enabled {
+            self.breakpoints.insert(address);
+        } else {
+            self.breakpoints.remove(&address);
+        }
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    // returns false if there was no such watchpoint
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|other| *other != watchpoint);
+        self.watchpoints.len() < count
+    }
+
+    // runs up to `ticks` instructions, stopping before an instruction with a breakpoint
+    // or after one that touched watched memory. Running again continues from there.
+    // An instruction that faults stops it too, at that instruction, until the next reset.
+    pub fn run(&mut self, ticks: u32) -> Option<Stop> {
+        for _ in 0..ticks {
+            if self.halted || self.waiting_for_vblank {
+                break;
+            }
+            if !self.at_breakpoint && self.breakpoints.contains(&self.program_counter) {
+                self.at_breakpoint = true;
+                return Some(Stop::Breakpoint(self.program_counter));
+            }
+            let access = if self.watchpoints.is_empty() { None } else { self.next_memory_access() };
+            self.tick();
+            if let Some(fault) = self.fault {
+                return Some(Stop::Fault(fault));
+            }
+            if let Some(access) = access {
+                if let Some(watchpoint) = self.watchpoints.iter().find(|watchpoint| watchpoint.triggered_by(&access)) {
+                    return Some(Stop::Watchpoint(*watchpoint));
+                }
+            }
+        }
+        None
+    }
+
+    // memory the next instruction will read or write
+    pub fn next_memory_access(&self) -> Option<MemoryAccess> {
+        cpu::memory_access(self, self.read_word(self.program_counter))
+    }
+
+    // runs the next instruction, ignoring breakpoints.
+    // A program waiting for the display is given its next frame first, so a step always runs something
+    pub fn step(&mut self) {
+        if self.waiting_for_vblank {
+            self.tick_timers();
+        }
+        self.tick();
     }
 
-    // pushes and popping values to and from the stack
-    pub(crate) fn push(&mut self, val: u16) {
-        self.stack[self.stack_pointer as usize] = val;
+    // pushes and popping values to and from the stack, a full or empty stack is a fault
+    pub(crate) fn push(&mut self, val: u16) -> Option<()> {
+        let Some(slot) = self.stack.get_mut(self.stack_pointer as usize) else {
+            self.raise(Fault::StackOverflow);
+            return None;
+        };
+        *slot = val;
         self.stack_pointer += 1;
+        Some(())
     }
-    pub(crate) fn pop(&mut self) -> u16 {
+    pub(crate) fn pop(&mut self) -> Option<u16> {
+        if self.stack_pointer == 0 {
+            self.raise(Fault::StackUnderflow);
+            return None;
+        }
         self.stack_pointer -= 1;
-        self.stack[self.stack_pointer as usize]
+        Some(self.stack[self.stack_pointer as usize])
+    }
+
+    // halts the program at the instruction just fetched, so debuggers show where it went wrong
+    pub(crate) fn raise(&mut self, fault: Fault) {
+        self.program_counter = self.program_counter.wrapping_sub(INSTRUCTION_SIZE);
+        self.halted = true;
+        self.fault = Some(fault);
     }
 
     pub(crate) fn next_instruction(&mut self){
-        self.program_counter += INSTRUCTION_SIZE;
+        self.program_counter = self.program_counter.wrapping_add(INSTRUCTION_SIZE);
+    }
+
+    // skips the next instruction, which is 4 bytes long for XO-CHIP's F000 NNNN
+    pub(crate) fn skip_instruction(&mut self) {
+        if self.platform.is_xochip() && self.read_word(self.program_counter) == 0xF000 {
+            self.next_instruction();
+        }
+        self.next_instruction();
+    }
+
+    // reads a big endian 16-bit value from ram
+    pub(crate) fn read_word(&self, address: u16) -> u16 {
+        let first_byte = self.ram[address as usize] as u16;
+        let second_byte = self.ram[address.wrapping_add(1) as usize] as u16;
+        (first_byte << 8) | second_byte
     }
 
     // CPU instructions
     pub fn tick(&mut self) {
+        // a halted program or one waiting for the display does nothing until the next frame
+        if self.halted || self.waiting_for_vblank {
+            return;
+        }
+        self.at_breakpoint = false;
         // Fetch
         let op = self.fetch();
         // Decode
@@ -102,25 +504,77 @@ impl Emulation {
     // fetches the next cpu instruction for execution
     // all instructions are 2 bytes -> returns 16-bit opcode combined as Big Endian
     fn fetch(&mut self) -> u16 {
-        let first_byte = self.ram[self.program_counter as usize] as u16;
-        let second_byte = self.ram[(self.program_counter + 1) as usize] as u16;
-        let op = (first_byte << 8) | second_byte;
+        let op = self.read_word(self.program_counter);
         self.next_instruction();
         op
     }
 
 
     pub fn tick_timers(&mut self) {
+        self.waiting_for_vblank = false;
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
-        // produces a beep when 0 is reached
+        // the buzzer sounds while the sound timer is above 0, see Audio::fill
         if self.sound_timer > 0 {
-            if self.sound_timer == 1 {
-                // TODO: implement sound output
-            }
             self.sound_timer -= 1;
         }
+        self.apply_cheats();
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_rom_resets_the_platform() {
+        let mut emu = Emulation::new();
+        emu.set_platform(Platform::XoChip);
+        emu.set_ticks_per_frame(1000);
+        emu.load(&[0x12, 0x00]);
+        assert!(emu.rom_info().is_none());
+        assert_eq!(emu.platform(), Platform::default());
+        assert_eq!(emu.quirks(), Platform::default().quirks());
+        assert_eq!(emu.ticks_per_frame(), Platform::default().default_tickrate());
+    }
+
+    #[test]
+    fn invalid_instructions_halt() {
+        let mut emu = Emulation::new();
+        emu.load(&[0x60, 0x01, 0xE0, 0x00]);
+        assert_eq!(emu.run(10), Some(Stop::Fault(Fault::InvalidInstruction(0xE000))));
+        assert_eq!(emu.program_counter(), START_ADDRESS + 2);
+        assert_eq!(emu.fault(), Some(Fault::InvalidInstruction(0xE000)));
+        assert_eq!(emu.run(10), None);
+        assert_eq!(emu.program_counter(), START_ADDRESS + 2);
+        emu.soft_reset();
+        assert_eq!(emu.fault(), None);
+    }
+
+    #[test]
+    fn stack_faults_halt() {
+        let mut emu = Emulation::new();
+        emu.load(&[0x00, 0xEE]);
+        assert_eq!(emu.run(10), Some(Stop::Fault(Fault::StackUnderflow)));
+        assert_eq!(emu.program_counter(), START_ADDRESS);
+
+        // calls itself until the stack is full
+        let mut emu = Emulation::new();
+        emu.load(&[0x22, 0x00]);
+        assert_eq!(emu.run(100), Some(Stop::Fault(Fault::StackOverflow)));
+        assert_eq!(emu.stack().len(), STACK_SIZE);
+        assert_eq!(emu.program_counter(), START_ADDRESS);
+    }
+
+    #[test]
+    fn waits_for_a_key_at_the_end_of_memory() {
+        let mut emu = Emulation::new();
+        emu.set_platform(Platform::XoChip);
+        emu.write_memory(0xFFFE, &[0xF0, 0x0A]);
+        emu.set_program_counter(0xFFFE);
+        emu.tick();
+        assert_eq!(emu.program_counter(), 0xFFFE);
+    }
+}
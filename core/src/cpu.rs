@@ -4,10 +4,12 @@
     The resulting nibbles are then matched to the corresponding instruction
     which is then executed.
     A copy of the instruction set can be found here: https://johnearnest.github.io/Octo/docs/chip8ref.pdf
+    SUPER-CHIP and XO-CHIP instructions are only executed on those platforms,
+    and quirks select between the behaviours of different interpreters.
+    The XO-CHIP extensions are described here: https://johnearnest.github.io/Octo/docs/XO-ChipSpecification.html
 */
-use rand::random;
-
-use crate::{chip, sprites};
+use crate::{chip, font, sprites};
+use crate::debug::{Fault, MemoryAccess};
 
 pub fn execute(emu: &mut chip::Emulation, op: u16) {
     let nibble1 = (op & 0xF000) >> 12;
@@ -17,15 +19,45 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
 
     match (nibble1, nibble2, nibble3, nibble4) {
         // NOP
-        (0, 0, 0, 0) => return,
+        (0, 0, 0, 0) => (),
         // CLS - Clear Screen
         (0, 0, 0xE, 0) => {
-            emu.frame_buffer = [false; chip::SCREEN_WIDTH * chip::SCREEN_HEIGHT];
+            sprites::clear(emu);
         },
         // RET - Return from Subroutine to the address stored on stack
         (0, 0, 0xE, 0xE) => {
-            let ret_addr = emu.pop();
-            emu.program_counter = ret_addr;
+            if let Some(ret_addr) = emu.pop() {
+                emu.program_counter = ret_addr;
+            }
+        },
+
+        //  - SUPER-CHIP display instructions -
+        // SCROLL DOWN N - scrolls the display down by N pixels
+        (0, 0, 0xC, _) if emu.platform.is_superchip() => {
+            sprites::scroll(emu, 0, nibble4 as isize);
+        },
+        // SCROLL UP N - XO-CHIP only, scrolls the display up by N pixels
+        (0, 0, 0xD, _) if emu.platform.is_xochip() => {
+            sprites::scroll(emu, 0, -(nibble4 as isize));
+        },
+        // SCROLL RIGHT - scrolls the display right by 4 pixels
+        (0, 0, 0xF, 0xB) if emu.platform.is_superchip() => {
+            sprites::scroll(emu, 4, 0);
+        },
+        // SCROLL LEFT - scrolls the display left by 4 pixels
+        (0, 0, 0xF, 0xC) if emu.platform.is_superchip() => {
+            sprites::scroll(emu, -4, 0);
+        },
+        // EXIT - stops the program
+        (0, 0, 0xF, 0xD) if emu.platform.is_superchip() => {
+            emu.halted = true;
+        },
+        // LORES / HIRES - switches between the 64x32 and 128x64 display
+        (0, 0, 0xF, 0xE) if emu.platform.is_superchip() => {
+            sprites::set_hires(emu, false);
+        },
+        (0, 0, 0xF, 0xF) if emu.platform.is_superchip() => {
+            sprites::set_hires(emu, true);
         },
         // JMP NNN - jump to a given address
         (1, _, _, _) => {
@@ -35,15 +67,16 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
         // CALL NNN - push current address to stack and go to subroutine address
         (2, _, _, _) => {
             let nnn = op & 0xFFF;
-            emu.push(emu.program_counter);
-            emu.program_counter = nnn;
+            if emu.push(emu.program_counter).is_some() {
+                emu.program_counter = nnn;
+            }
         },
         // SKIP VX == NN - if register x == NN skip to the next line (ifelse)
         (3, _, _, _) => {
             let x = nibble2 as usize;
             let nn = (op & 0xFF) as u8;
             if emu.registers[x] == nn {
-                emu.next_instruction();
+                emu.skip_instruction();
             }
         },
 
@@ -52,7 +85,7 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             let x = nibble2 as usize;
             let nn = (op & 0xFF) as u8;
             if emu.registers[x] != nn {
-                emu.next_instruction();
+                emu.skip_instruction();
             }
         },
 
@@ -61,7 +94,23 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             let x = nibble2 as usize;
             let y = nibble3 as usize;
             if emu.registers[x] == emu.registers[y] {
-                emu.next_instruction();
+                emu.skip_instruction();
+            }
+        },
+
+        // SAVE VX - VY - XO-CHIP only, stores the registers x to y (in either order) in RAM, I is unchanged
+        (5, _, _, 2) if emu.platform.is_xochip() => {
+            for (offset, index) in register_range(nibble2, nibble3).enumerate() {
+                let address = emu.index_register.wrapping_add(offset as u16);
+                emu.ram[address as usize] = emu.registers[index];
+            }
+        },
+
+        // LOAD VX - VY - XO-CHIP only, loads the registers x to y (in either order) from RAM, I is unchanged
+        (5, _, _, 3) if emu.platform.is_xochip() => {
+            for (offset, index) in register_range(nibble2, nibble3).enumerate() {
+                let address = emu.index_register.wrapping_add(offset as u16);
+                emu.registers[index] = emu.ram[address as usize];
             }
         },
 
@@ -92,6 +141,9 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             let x = nibble2 as usize;
             let y = nibble3 as usize;
             emu.registers[x] |= emu.registers[y];
+            if emu.quirks.logic {
+                emu.registers[0xF] = 0;
+            }
         },
 
         // VX &= VY
@@ -99,12 +151,18 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             let x = nibble2 as usize;
             let y = nibble3 as usize;
             emu.registers[x] &= emu.registers[y];
+            if emu.quirks.logic {
+                emu.registers[0xF] = 0;
+            }
         },
         // VX ^= VY
         (8, _, _, 3) => {
             let x = nibble2 as usize;
             let y = nibble3 as usize;
             emu.registers[x] ^= emu.registers[y];
+            if emu.quirks.logic {
+                emu.registers[0xF] = 0;
+            }
         },
 
         // VX += VY - adds y's value to x, and sets carry flag in case of overflow
@@ -126,8 +184,12 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
         },
 
         // VX >>= 1, right bit shift on register x's value, dropped bit stored in f register
+        // without the shift quirk register y is shifted into register x
         (8, _, _, 6) => {
             let x = nibble2 as usize;
+            if !emu.quirks.shift {
+                emu.registers[x] = emu.registers[nibble3 as usize];
+            }
             let lsb = emu.registers[x] & 1;
             emu.registers[x] >>= 1;
             emu.registers[0xF] = lsb;
@@ -145,6 +207,9 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
         // VX <<= 1, bit shift left storing overflow bit into f register
         (8, _, _, 0xE) => {
             let x = nibble2 as usize;
+            if !emu.quirks.shift {
+                emu.registers[x] = emu.registers[nibble3 as usize];
+            }
             let msb = (emu.registers[x] >> 7) & 1;
             emu.registers[x] <<= 1;
             emu.registers[0xF] = msb;
@@ -155,7 +220,7 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             let x = nibble2 as usize;
             let y = nibble3 as usize;
             if emu.registers[x] != emu.registers[y] {
-                emu.next_instruction();
+                emu.skip_instruction();
             }
         },
         // I = NNN - sets the i register (pointer to ram address)
@@ -165,16 +230,18 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
         },
 
         // JMP V0 + NNN - jumps to the sum of register 0 and NNN
+        // with the jump quirk register x is used instead of register 0
         (0xB, _, _, _) => {
             let nnn = op & 0xFFF;
-            emu.program_counter = (emu.registers[0] as u16) + nnn;
+            let register = if emu.quirks.jump { nibble2 as usize } else { 0 };
+            emu.program_counter = (emu.registers[register] as u16) + nnn;
         },
 
         // VX = rand() & NN - RNG generator, takes a random u8 and ands with NN
         (0xC, _, _, _) => {
             let x = nibble2 as usize;
             let nn = (op & 0xFF) as u8;
-            let rng: u8 = random();
+            let rng = emu.random();
             emu.registers[x] = rng & nn;
         },
 
@@ -188,9 +255,9 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
         (0xE, _, 9, 0xE) => {
             let x = nibble2 as usize;
             let x_value = emu.registers[x];
-            let key = emu.keys[x_value as usize];
+            let key = emu.keys[x_value as usize & 0xF];
             if key {
-                emu.next_instruction();
+                emu.skip_instruction();
             }
         },
 
@@ -198,11 +265,37 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
         (0xE, _, 0xA, 1) => {
             let x = nibble2 as usize;
             let x_value = emu.registers[x];
-            let key = emu.keys[x_value as usize];
+            let key = emu.keys[x_value as usize & 0xF];
             if !key {
-                emu.next_instruction()
+                emu.skip_instruction()
             }
         },
+        //  - XO-CHIP instructions -
+        // I = NNNN - loads a 16-bit address from the next two bytes into the index register
+        (0xF, 0, 0, 0) if emu.platform.is_xochip() => {
+            emu.index_register = emu.read_word(emu.program_counter);
+            emu.next_instruction();
+        },
+
+        // PLANE N - selects the bitplanes drawn to, cleared and scrolled
+        (0xF, _, 0, 1) if emu.platform.is_xochip() => {
+            emu.planes = (nibble2 & 0x3) as u8;
+        },
+
+        // AUDIO - loads a 16 byte audio pattern from RAM at the index register
+        (0xF, 0, 0, 2) if emu.platform.is_xochip() => {
+            for offset in 0..emu.audio_pattern.len() {
+                let address = emu.index_register.wrapping_add(offset as u16);
+                emu.audio_pattern[offset] = emu.ram[address as usize];
+            }
+            emu.pattern_loaded = true;
+        },
+
+        // PITCH = VX - sets the playback rate of the audio pattern
+        (0xF, _, 3, 0xA) if emu.platform.is_xochip() => {
+            emu.pitch = emu.registers[nibble2 as usize];
+        },
+
         // VX = DT - stores the current state of the delay timer in register X
         (0xF, _, 0, 7) => {
             let x = nibble2 as usize;
@@ -222,7 +315,7 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             }
             if !pressed {
                 // loop and try again
-                emu.program_counter -= chip::INSTRUCTION_SIZE;
+                emu.program_counter = emu.program_counter.wrapping_sub(chip::INSTRUCTION_SIZE);
             }
         },
 
@@ -255,6 +348,13 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             emu.index_register = char * 5;
         },
 
+        // I = BIG FONT - SUPER-CHIP only, sets the index register to the large 8x10 character of nibble's value
+        (0xF, _, 3, 0) if emu.platform.is_superchip() => {
+            let x = nibble2 as usize;
+            let char = (emu.registers[x] & 0xF) as u16;
+            emu.index_register = chip::BIG_FONT_ADDRESS as u16 + char * (font::BIG_SET_SIZE / 16) as u16;
+        },
+
         // BCD - stores the binary coded digit of x into the ram (hex to dec)
         (0xF, _, 3, 3) => {
             let x = nibble2 as usize;
@@ -266,29 +366,90 @@ pub fn execute(emu: &mut chip::Emulation, op: u16) {
             let ones = (x_value % 10.0) as u8;
 
             emu.ram[emu.index_register as usize] = hundreds;
-            emu.ram[(emu.index_register + 1) as usize] = tens;
-            emu.ram[(emu.index_register + 2) as usize] = ones;
+            emu.ram[emu.index_register.wrapping_add(1) as usize] = tens;
+            emu.ram[emu.index_register.wrapping_add(2) as usize] = ones;
         },
 
         // STORE V0 - VX - stores the values of the first register up to x register in RAM
         (0xF, _, 5, 5) => {
             let x = nibble2 as usize;
-            let i_reg = emu.index_register as usize;
             for index in 0..=x {
-                emu.ram[i_reg + index] = emu.registers[index];
+                let address = emu.index_register.wrapping_add(index as u16);
+                emu.ram[address as usize] = emu.registers[index];
             }
+            increment_index_register(emu, x);
         },
 
         // LOAD V0 - VX - loads the values of RAM into the registers from reg 0 to reg x
         (0xF, _, 6, 5) => {
             let x = nibble2 as usize;
-            let i_reg = emu.index_register as usize;
             for index in 0..=x {
-                emu.registers[index] = emu.ram[i_reg + index];
+                let address = emu.index_register.wrapping_add(index as u16);
+                emu.registers[index] = emu.ram[address as usize];
             }
+            increment_index_register(emu, x);
         },
 
-        // all basic instructions of chip8 implemented. but chip8 extensions are currently not:
-        (_, _, _, _) => unimplemented!("opcode: {} is not implemented", op),
+        // SAVE FLAGS V0 - VX - SUPER-CHIP only, stores registers in the persistent RPL user flags
+        (0xF, _, 7, 5) if emu.platform.is_superchip() => {
+            let x = nibble2 as usize;
+            emu.flags[..=x].copy_from_slice(&emu.registers[..=x]);
+        },
+
+        // LOAD FLAGS V0 - VX - SUPER-CHIP only, loads registers from the RPL user flags
+        (0xF, _, 8, 5) if emu.platform.is_superchip() => {
+            let x = nibble2 as usize;
+            emu.registers[..=x].copy_from_slice(&emu.flags[..=x]);
+        },
+
+        // instructions of other chip8 extensions, or of a platform other than the current one
+        (_, _, _, _) => emu.raise(Fault::InvalidInstruction(op)),
+    }
+}
+
+// memory the instruction reads or writes, apart from its own bytes
+pub fn memory_access(emu: &chip::Emulation, op: u16) -> Option<MemoryAccess> {
+    let nibble1 = (op & 0xF000) >> 12;
+    let nibble2 = (op & 0x0F00) >> 8;
+    let nibble3 = (op & 0x00F0) >> 4;
+    let nibble4 = op & 0x000F;
+    let access = |length: u16, write: bool| Some(MemoryAccess { address: emu.index_register, length, write });
+
+    match (nibble1, nibble2, nibble3, nibble4) {
+        (5, _, _, 2) if emu.platform.is_xochip() => access(nibble2.abs_diff(nibble3) + 1, true),
+        (5, _, _, 3) if emu.platform.is_xochip() => access(nibble2.abs_diff(nibble3) + 1, false),
+        // each selected bitplane reads its own sprite
+        (0xD, _, _, _) => {
+            let bytes = if nibble4 == 0 && emu.platform.is_superchip() { 32 } else { nibble4 };
+            let length = bytes * (emu.planes & 0x3).count_ones() as u16;
+            if length == 0 {
+                return None;
+            }
+            access(length, false)
+        },
+        (0xF, 0, 0, 2) if emu.platform.is_xochip() => access(emu.audio_pattern.len() as u16, false),
+        (0xF, _, 3, 3) => access(3, true),
+        (0xF, _, 5, 5) => access(nibble2 + 1, true),
+        (0xF, _, 6, 5) => access(nibble2 + 1, false),
+        _ => None,
+    }
+}
+
+// FX55 and FX65 move the index register past the registers, unless a quirk says otherwise
+fn increment_index_register(emu: &mut chip::Emulation, x: usize) {
+    if emu.quirks.memory_leave_i_unchanged {
+        return;
+    }
+    let increment = if emu.quirks.memory_increment_by_x { x } else { x + 1 };
+    emu.index_register = emu.index_register.wrapping_add(increment as u16);
+}
+
+// registers x to y, counting down if y is smaller than x
+fn register_range(x: u16, y: u16) -> Box<dyn Iterator<Item = usize>> {
+    let (x, y) = (x as usize, y as usize);
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
     }
-}
\ No newline at end of file
+}
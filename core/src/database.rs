@@ -0,0 +1,131 @@
+/*
+    ROM database.
+    The files in core/database follow the schema of the community chip-8-database
+    (https://github.com/chip-8/chip-8-database) and can be replaced by newer versions of it:
+    sha1-hashes.json maps the SHA-1 of a ROM to its program in programs.json,
+    and platforms.json holds the default quirks and tick rate of each platform.
+*/
+use std::sync::OnceLock;
+
+use serde_json::Value;
+
+use crate::platform::{Platform, Quirks};
+
+const PROGRAMS: &str = include_str!("../database/programs.json");
+const HASHES: &str = include_str!("../database/sha1-hashes.json");
+const PLATFORMS: &str = include_str!("../database/platforms.json");
+
+struct Database {
+    programs: Value,
+    hashes: Value,
+    platforms: Value,
+}
+
+fn database() -> &'static Database {
+    static DATABASE: OnceLock<Database> = OnceLock::new();
+    DATABASE.get_or_init(|| Database {
+        programs: serde_json::from_str(PROGRAMS).expect("invalid programs.json"),
+        hashes: serde_json::from_str(HASHES).expect("invalid sha1-hashes.json"),
+        platforms: serde_json::from_str(PLATFORMS).expect("invalid platforms.json"),
+    })
+}
+
+// what the database knows about a ROM
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RomInfo {
+    pub title: String,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub tickrate: u32,
+    // background first, then the colours of the bitplane combinations
+    pub colors: Vec<[u8; 3]>,
+    // hints like ("left", 0x4), in the database's naming
+    pub keys: Vec<(String, u8)>,
+}
+
+// looks up a ROM by its lowercase hex SHA-1
+pub fn lookup(hash: &str) -> Option<RomInfo> {
+    let db = database();
+    let index = db.hashes.get(hash)?.as_u64()? as usize;
+    let program = db.programs.get(index)?;
+    let rom = program.get("roms")?.get(hash)?;
+
+    // the first listed platform this emulator supports is used
+    let platform = rom
+        .get("platforms")?
+        .as_array()?
+        .iter()
+        .filter_map(|id| Platform::from_id(id.as_str()?))
+        .next()?;
+
+    // some ROMs need quirks that differ from their platform's defaults
+    let mut quirks = platform.quirks();
+    if let Some(overrides) = rom.get("quirkyPlatforms").and_then(|q| q.get(platform.id())) {
+        for name in Quirks::NAMES {
+            if let Some(value) = overrides.get(name).and_then(Value::as_bool) {
+                quirks.set(name, value);
+            }
+        }
+    }
+
+    let tickrate = rom
+        .get("tickrate")
+        .and_then(Value::as_u64)
+        .map(|tickrate| tickrate as u32)
+        .unwrap_or_else(|| platform.default_tickrate());
+
+    let colors = rom
+        .get("colors")
+        .and_then(|colors| colors.get("pixels"))
+        .and_then(Value::as_array)
+        .map(|pixels| pixels.iter().filter_map(|color| parse_color(color.as_str()?)).collect())
+        .unwrap_or_default();
+
+    let keys = rom
+        .get("keys")
+        .and_then(Value::as_object)
+        .map(|keys| {
+            keys.iter()
+                .filter_map(|(name, key)| Some((name.clone(), key.as_u64().filter(|&k| k < 16)? as u8)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(RomInfo {
+        title: program.get("title")?.as_str()?.to_string(),
+        platform,
+        quirks,
+        tickrate,
+        colors,
+        keys,
+    })
+}
+
+// default quirks and tick rate of a platform
+pub(crate) fn platform_defaults(platform: Platform) -> (Quirks, u32) {
+    let entry = database()
+        .platforms
+        .as_array()
+        .and_then(|platforms| {
+            platforms.iter().find(|entry| entry.get("id").and_then(Value::as_str) == Some(platform.id()))
+        })
+        .expect("platform missing from platforms.json");
+
+    let mut quirks = Quirks::default();
+    for name in Quirks::NAMES {
+        let value = entry["quirks"].get(name).and_then(Value::as_bool).unwrap_or(false);
+        quirks.set(name, value);
+    }
+    let tickrate = entry["defaultTickrate"].as_u64().unwrap_or(15) as u32;
+    (quirks, tickrate)
+}
+
+// colours are written as #rrggbb
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
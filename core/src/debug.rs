@@ -0,0 +1,80 @@
+/*
+    Watchpoints stop the emulation when an instruction reads or writes memory in a range,
+    for debuggers that want to know which code touches a variable.
+    The memory an instruction uses is worked out before it runs, the emulation stops after it ran.
+    Faults stop a program that runs into something it can't do, like data that isn't an instruction.
+*/
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WatchKind {
+    Read,
+    Write,
+    // reads and writes
+    Access,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Watchpoint {
+    pub address: u16,
+    pub length: u16,
+    pub kind: WatchKind,
+}
+
+// memory read or written by an instruction, addresses wrap around at the end of memory
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MemoryAccess {
+    pub address: u16,
+    pub length: u16,
+    pub write: bool,
+}
+
+// why Emulation::run stopped early
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stop {
+    // before the instruction at the address
+    Breakpoint(u16),
+    // after the instruction that touched the watched memory
+    Watchpoint(Watchpoint),
+    // at the instruction that couldn't run, the program stays halted
+    Fault(Fault),
+}
+
+// why a program can't go on
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fault {
+    // an opcode that is no instruction of the current platform
+    InvalidInstruction(u16),
+    // a call with all 16 levels of the stack in use
+    StackOverflow,
+    // a return with nothing on the stack
+    StackUnderflow,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::InvalidInstruction(opcode) => write!(f, "invalid instruction {:04X}", opcode),
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::StackUnderflow => write!(f, "return with an empty stack"),
+        }
+    }
+}
+
+impl MemoryAccess {
+    pub fn overlaps(&self, address: u16, length: u16) -> bool {
+        (self.address.wrapping_sub(address) as u32) < length as u32
+            || (address.wrapping_sub(self.address) as u32) < self.length as u32
+    }
+}
+
+impl Watchpoint {
+    pub fn triggered_by(&self, access: &MemoryAccess) -> bool {
+        let kind = match self.kind {
+            WatchKind::Read => !access.write,
+            WatchKind::Write => access.write,
+            WatchKind::Access => true,
+        };
+        kind && self.length > 0 && access.overlaps(self.address, self.length)
+    }
+}
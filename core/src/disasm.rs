@@ -0,0 +1,99 @@
+/*
+    Turns instructions back into Octo-style assembly mnemonics for debuggers.
+    SUPER-CHIP and XO-CHIP instructions are always decoded, whatever the platform,
+    anything that is not an instruction is shown as a data word.
+*/
+use crate::chip::{self, Emulation};
+
+// a disassembled instruction, size is 4 for XO-CHIP's F000 NNNN and 2 otherwise
+pub struct Line {
+    pub address: u16,
+    pub opcode: u16,
+    pub size: u16,
+    pub text: String,
+}
+
+// disassembles count instructions starting at address
+pub fn disassemble(emu: &Emulation, address: u16, count: usize) -> Vec<Line> {
+    let mut lines = Vec::with_capacity(count);
+    let mut address = address;
+    for _ in 0..count {
+        let opcode = emu.read_word(address);
+        let next = emu.read_word(address.wrapping_add(chip::INSTRUCTION_SIZE));
+        let size = if opcode == 0xF000 { 4 } else { 2 };
+        lines.push(Line { address, opcode, size, text: instruction(opcode, next) });
+        address = address.wrapping_add(size);
+    }
+    lines
+}
+
+// disassembles the instructions around an address, with `before` instructions leading up to it.
+// Instructions can't be decoded backwards, so the lines before assume 2-byte instructions.
+pub fn around(emu: &Emulation, address: u16, before: usize, count: usize) -> Vec<Line> {
+    let start = address.saturating_sub(before as u16 * chip::INSTRUCTION_SIZE);
+    disassemble(emu, start, count)
+}
+
+// mnemonic of a single instruction, next is the word after it, used by F000 NNNN
+pub fn instruction(opcode: u16, next: u16) -> String {
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+    let n = opcode & 0x000F;
+    let nn = opcode & 0x00FF;
+    let nnn = opcode & 0x0FFF;
+
+    match (opcode >> 12, x, y, n) {
+        (0, 0, 0xE, 0) => String::from("CLS"),
+        (0, 0, 0xE, 0xE) => String::from("RET"),
+        (0, 0, 0xC, _) => format!("SCD {}", n),
+        (0, 0, 0xD, _) => format!("SCU {}", n),
+        (0, 0, 0xF, 0xB) => String::from("SCR"),
+        (0, 0, 0xF, 0xC) => String::from("SCL"),
+        (0, 0, 0xF, 0xD) => String::from("EXIT"),
+        (0, 0, 0xF, 0xE) => String::from("LOW"),
+        (0, 0, 0xF, 0xF) => String::from("HIGH"),
+        (0, _, _, _) => format!("SYS {:03X}", nnn),
+        (1, _, _, _) => format!("JP {:03X}", nnn),
+        (2, _, _, _) => format!("CALL {:03X}", nnn),
+        (3, _, _, _) => format!("SE V{:X}, {:02X}", x, nn),
+        (4, _, _, _) => format!("SNE V{:X}, {:02X}", x, nn),
+        (5, _, _, 0) => format!("SE V{:X}, V{:X}", x, y),
+        (5, _, _, 2) => format!("SAVE V{:X}-V{:X}", x, y),
+        (5, _, _, 3) => format!("LOAD V{:X}-V{:X}", x, y),
+        (6, _, _, _) => format!("LD V{:X}, {:02X}", x, nn),
+        (7, _, _, _) => format!("ADD V{:X}, {:02X}", x, nn),
+        (8, _, _, 0) => format!("LD V{:X}, V{:X}", x, y),
+        (8, _, _, 1) => format!("OR V{:X}, V{:X}", x, y),
+        (8, _, _, 2) => format!("AND V{:X}, V{:X}", x, y),
+        (8, _, _, 3) => format!("XOR V{:X}, V{:X}", x, y),
+        (8, _, _, 4) => format!("ADD V{:X}, V{:X}", x, y),
+        (8, _, _, 5) => format!("SUB V{:X}, V{:X}", x, y),
+        (8, _, _, 6) => format!("SHR V{:X}, V{:X}", x, y),
+        (8, _, _, 7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (9, _, _, 0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, _, _, _) => format!("LD I, {:03X}", nnn),
+        (0xB, _, _, _) => format!("JP V0, {:03X}", nnn),
+        (0xC, _, _, _) => format!("RND V{:X}, {:02X}", x, nn),
+        (0xD, _, _, _) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 1) => format!("SKNP V{:X}", x),
+        (0xF, 0, 0, 0) => format!("LD I, {:04X}", next),
+        (0xF, _, 0, 1) => format!("PLANE {}", x),
+        (0xF, 0, 0, 2) => String::from("AUDIO"),
+        (0xF, _, 0, 7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 1, 5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 1, 8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 2, 9) => format!("LD F, V{:X}", x),
+        (0xF, _, 3, 0) => format!("LD HF, V{:X}", x),
+        (0xF, _, 3, 3) => format!("LD B, V{:X}", x),
+        (0xF, _, 3, 0xA) => format!("PITCH V{:X}", x),
+        (0xF, _, 5, 5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 6, 5) => format!("LD V{:X}, [I]", x),
+        (0xF, _, 7, 5) => format!("LD R, V{:X}", x),
+        (0xF, _, 8, 5) => format!("LD V{:X}, R", x),
+        _ => format!("DW {:04X}", opcode),
+    }
+}
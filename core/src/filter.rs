@@ -0,0 +1,169 @@
+/*
+    Display filters against flicker.
+    CHIP-8 programs erase sprites by drawing them again, so moving objects are missing
+    from every other frame. The filters remember earlier frames and show their pixels too:
+    Decay(n) fades pixels out over n frames like a phosphor screen, Blend mixes the last
+    two frames and Or(n) shows every pixel set in any of the last n frames.
+    Frames are pushed once per emulated frame, after tick_timers().
+*/
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::chip::Emulation;
+use crate::palette::Palette;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FilterMode {
+    #[default]
+    Off,
+    Decay(u8),
+    Blend,
+    Or(u8),
+}
+
+// frames used by decay and or when none are given
+pub const DEFAULT_FILTER_FRAMES: u8 = 4;
+
+impl FilterMode {
+    // off, blend, decay, decay:N, or, or:N
+    pub fn parse(text: &str) -> Option<FilterMode> {
+        let (name, frames) = match text.split_once(':') {
+            Some((name, frames)) => (name, Some(frames.parse().ok().filter(|frames| *frames >= 1)?)),
+            None => (text, None),
+        };
+        let frames = frames.unwrap_or(DEFAULT_FILTER_FRAMES);
+        match name {
+            "off" => Some(FilterMode::Off),
+            "blend" => Some(FilterMode::Blend),
+            "decay" => Some(FilterMode::Decay(frames)),
+            "or" => Some(FilterMode::Or(frames)),
+            _ => None,
+        }
+    }
+
+    // the next mode when cycling through them, decay and or use the given number of frames
+    pub fn next(self, frames: u8) -> FilterMode {
+        match self {
+            FilterMode::Off => FilterMode::Decay(frames),
+            FilterMode::Decay(_) => FilterMode::Blend,
+            FilterMode::Blend => FilterMode::Or(frames),
+            FilterMode::Or(_) => FilterMode::Off,
+        }
+    }
+}
+
+impl fmt::Display for FilterMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterMode::Off => write!(f, "off"),
+            FilterMode::Decay(frames) => write!(f, "decay:{}", frames),
+            FilterMode::Blend => write!(f, "blend"),
+            FilterMode::Or(frames) => write!(f, "or:{}", frames),
+        }
+    }
+}
+
+pub struct Filter {
+    mode: FilterMode,
+    width: usize,
+    height: usize,
+    // the latest frame first
+    frames: VecDeque<Vec<u8>>,
+    // for decay, the planes each pixel was last set in and the frames since then
+    last_set: Vec<u8>,
+    age: Vec<u8>,
+}
+
+impl Filter {
+    pub fn new(mode: FilterMode) -> Self {
+        Filter { mode, width: 0, height: 0, frames: VecDeque::new(), last_set: Vec::new(), age: Vec::new() }
+    }
+
+    pub fn mode(&self) -> FilterMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: FilterMode) {
+        self.mode = mode;
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // remembers the current display, switching resolution forgets the earlier frames
+    pub fn push(&mut self, emu: &Emulation) {
+        let display = emu.get_display();
+        if emu.display_width() != self.width || emu.display_height() != self.height {
+            self.width = emu.display_width();
+            self.height = emu.display_height();
+            self.frames.clear();
+            self.last_set = vec![0; display.len()];
+            self.age = vec![u8::MAX; display.len()];
+        }
+        let kept = match self.mode {
+            FilterMode::Or(frames) => frames as usize,
+            FilterMode::Blend => 2,
+            _ => 1,
+        };
+        let mut frame = if self.frames.len() >= kept { self.frames.pop_back().unwrap() } else { Vec::new() };
+        frame.clear();
+        frame.extend_from_slice(display);
+        self.frames.push_front(frame);
+        self.frames.truncate(kept);
+
+        for ((pixel, last_set), age) in display.iter().zip(self.last_set.iter_mut()).zip(self.age.iter_mut()) {
+            if *pixel != 0 {
+                *last_set = *pixel;
+                *age = 0;
+            } else {
+                *age = age.saturating_add(1);
+            }
+        }
+    }
+
+    // forgets the earlier frames, e.g. after a reset
+    pub fn clear(&mut self) {
+        self.width = 0;
+        self.height = 0;
+        self.frames.clear();
+    }
+
+    // colours of the filtered display, row by row, width() pixels per row
+    pub fn pixels<'a>(&'a self, palette: &'a Palette) -> impl Iterator<Item = [u8; 3]> + 'a {
+        (0..self.width * self.height).map(move |index| self.pixel(index, palette))
+    }
+
+    fn pixel(&self, index: usize, palette: &Palette) -> [u8; 3] {
+        let latest = self.frames.front().map_or(0, |frame| frame[index]);
+        match self.mode {
+            FilterMode::Off => palette.color(latest),
+            FilterMode::Decay(frames) => {
+                let age = self.age[index];
+                if age >= frames {
+                    return palette.color(0);
+                }
+                let strength = (frames - age) as u32 * 256 / frames as u32;
+                mix(palette.color(0), palette.color(self.last_set[index]), strength)
+            },
+            FilterMode::Blend => {
+                let previous = self.frames.get(1).map_or(latest, |frame| frame[index]);
+                mix(palette.color(previous), palette.color(latest), 128)
+            },
+            FilterMode::Or(_) => palette.color(self.frames.iter().fold(0, |planes, frame| planes | frame[index])),
+        }
+    }
+}
+
+// mixes two colours, strength 0 gives the first one and 256 the second
+fn mix(from: [u8; 3], to: [u8; 3], strength: u32) -> [u8; 3] {
+    let mut color = [0; 3];
+    for ((channel, from), to) in color.iter_mut().zip(from).zip(to) {
+        *channel = ((from as u32 * (256 - strength) + to as u32 * strength) / 256) as u8;
+    }
+    color
+}
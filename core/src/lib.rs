@@ -2,7 +2,24 @@ mod font;
 mod chip;
 mod cpu;
 mod sprites;
+mod platform;
+mod state;
+pub mod audio;
+pub mod cheats;
+pub mod database;
+pub mod debug;
+pub mod disasm;
+pub mod filter;
+pub mod palette;
 
 pub use chip::Emulation;
+pub use font::SET as FONT_SET;
+pub use chip::MAX_ROM_SIZE;
 pub use chip::SCREEN_HEIGHT;
 pub use chip::SCREEN_WIDTH;
+pub use chip::HIRES_SCREEN_HEIGHT;
+pub use chip::HIRES_SCREEN_WIDTH;
+pub use platform::Platform;
+pub use platform::Quirks;
+pub use state::StateError;
+pub use state::STATE_SIZE;
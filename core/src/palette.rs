@@ -0,0 +1,50 @@
+/*
+    Colour palettes for the frontends.
+    A palette has four colours: the background, then the colours of pixels set in
+    bitplane 1, bitplane 2 and both planes. Programs that only use the first plane
+    only show the first two.
+*/
+use crate::database::RomInfo;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Palette {
+    pub colors: [[u8; 3]; 4],
+}
+
+// built-in themes, the first is the default
+pub const THEMES: [(&str, Palette); 6] = [
+    ("feo8", Palette { colors: [[185, 55, 94], [255, 122, 162], [120, 20, 60], [255, 200, 215]] }),
+    ("classic", Palette { colors: [[0, 0, 0], [255, 255, 255], [170, 170, 170], [85, 85, 85]] }),
+    ("green", Palette { colors: [[8, 24, 8], [51, 255, 102], [26, 128, 51], [153, 255, 179]] }),
+    ("amber", Palette { colors: [[26, 15, 0], [255, 176, 0], [128, 88, 0], [255, 216, 128]] }),
+    ("contrast", Palette { colors: [[0, 0, 0], [255, 255, 0], [0, 255, 255], [255, 255, 255]] }),
+    ("octo", Palette { colors: [[153, 102, 0], [255, 204, 0], [255, 102, 0], [102, 34, 0]] }),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        THEMES[0].1
+    }
+}
+
+impl Palette {
+    pub fn theme(name: &str) -> Option<Palette> {
+        THEMES.iter().find(|(theme, _)| theme.eq_ignore_ascii_case(name)).map(|(_, palette)| *palette)
+    }
+
+    // the default palette with the colours the rom database has for the program
+    pub fn for_rom(info: Option<&RomInfo>) -> Palette {
+        let mut palette = Palette::default();
+        if let Some(info) = info {
+            for (color, rom_color) in palette.colors.iter_mut().zip(&info.colors) {
+                *color = *rom_color;
+            }
+        }
+        palette
+    }
+
+    // colour of a pixel of the display, which holds a bitmask of its bitplanes
+    pub fn color(&self, pixel: u8) -> [u8; 3] {
+        self.colors[pixel as usize & 3]
+    }
+}
@@ -0,0 +1,119 @@
+/*
+    Platforms are the different CHIP-8 interpreters programs were written for.
+    They differ in the instructions they support and in a set of quirks,
+    small differences in how some of the original instructions behave.
+    The ids, default quirks and tick rates match the chip-8-database platforms.
+*/
+use crate::database;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6/8XYE shift VX in place instead of shifting VY into VX
+    pub shift: bool,
+    // FX55/FX65 increment I by X instead of X + 1
+    pub memory_increment_by_x: bool,
+    // FX55/FX65 leave I unchanged
+    pub memory_leave_i_unchanged: bool,
+    // sprites wrap around the edges of the screen instead of being clipped
+    pub wrap: bool,
+    // BNNN jumps to VX + NNN, where X is the highest nibble of NNN
+    pub jump: bool,
+    // drawing a sprite waits for the next frame
+    pub vblank: bool,
+    // 8XY1/8XY2/8XY3 reset VF to 0
+    pub logic: bool,
+}
+
+impl Quirks {
+    // names as used by the chip-8-database
+    pub const NAMES: [&'static str; 7] = [
+        "shift", "memoryIncrementByX", "memoryLeaveIUnchanged", "wrap", "jump", "vblank", "logic",
+    ];
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "shift" => Some(self.shift),
+            "memoryIncrementByX" => Some(self.memory_increment_by_x),
+            "memoryLeaveIUnchanged" => Some(self.memory_leave_i_unchanged),
+            "wrap" => Some(self.wrap),
+            "jump" => Some(self.jump),
+            "vblank" => Some(self.vblank),
+            "logic" => Some(self.logic),
+            _ => None,
+        }
+    }
+
+    // sets a quirk by name, returns false if there is no such quirk
+    pub fn set(&mut self, name: &str, value: bool) -> bool {
+        let quirk = match name {
+            "shift" => &mut self.shift,
+            "memoryIncrementByX" => &mut self.memory_increment_by_x,
+            "memoryLeaveIUnchanged" => &mut self.memory_leave_i_unchanged,
+            "wrap" => &mut self.wrap,
+            "jump" => &mut self.jump,
+            "vblank" => &mut self.vblank,
+            "logic" => &mut self.logic,
+            _ => return false,
+        };
+        *quirk = value;
+        true
+    }
+}
+
+// programs not in the database run as modern CHIP-8
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Platform {
+    OriginalChip8,
+    #[default]
+    ModernChip8,
+    Chip48,
+    SuperChip1,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 6] = [
+        Platform::OriginalChip8,
+        Platform::ModernChip8,
+        Platform::Chip48,
+        Platform::SuperChip1,
+        Platform::SuperChip,
+        Platform::XoChip,
+    ];
+
+    // id as used by the chip-8-database
+    pub fn id(&self) -> &'static str {
+        match self {
+            Platform::OriginalChip8 => "originalChip8",
+            Platform::ModernChip8 => "modernChip8",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip1 => "superchip1",
+            Platform::SuperChip => "superchip",
+            Platform::XoChip => "xochip",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Platform> {
+        Platform::ALL.iter().copied().find(|platform| platform.id() == id)
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        database::platform_defaults(*self).0
+    }
+
+    // instructions per frame at 60 frames per second
+    pub fn default_tickrate(&self) -> u32 {
+        database::platform_defaults(*self).1
+    }
+
+    // SUPER-CHIP instructions: high resolution, scrolling, large sprites and fonts, user flags
+    pub fn is_superchip(&self) -> bool {
+        matches!(self, Platform::SuperChip1 | Platform::SuperChip | Platform::XoChip)
+    }
+
+    // XO-CHIP instructions: bitplanes, audio patterns and 16-bit addresses
+    pub fn is_xochip(&self) -> bool {
+        *self == Platform::XoChip
+    }
+}
@@ -3,38 +3,102 @@ use crate::chip;
 /*
 Sprites are always 8 pixels wide but can be a variable amount of pixels from 1 to 16 pixels tall
 Height is specified in the fourth nibble (N) of the DXYN opcode
+SUPER-CHIP and XO-CHIP draw a 16x16 sprite when N is 0, stored as two bytes per row
 The sprites are stored row by row beginning with the address stored in register I.
+With XO-CHIP each selected bitplane reads its own sprite, one after the other.
 Register F is set when bits are flipped (from black pixel to white or vice versa)
 */
 
 pub fn draw_sprite(emu: &mut chip::Emulation, nibble2:u16, nibble3:u16, nibble4:u16){
-    // nibble 2 and 3 specify the x and y coordinates to draw to
-    let screen_x = emu.registers[nibble2 as usize] as u16;
-    let screen_y = emu.registers[nibble3 as usize] as u16;
+    let width = emu.display_width();
+    let height = emu.display_height();
+    // nibble 2 and 3 specify the x and y coordinates to draw to, the start always wraps around
+    let screen_x = emu.registers[nibble2 as usize] as usize % width;
+    let screen_y = emu.registers[nibble3 as usize] as usize % height;
     // nibble 4 specifies the height of the sprite
-    let sprite_height = nibble4;
+    let (sprite_width, sprite_height) = if nibble4 == 0 && emu.platform.is_superchip() {
+        (16, 16)
+    } else {
+        (8, nibble4 as usize)
+    };
 
+    let mut address = emu.index_register;
     let mut flipped_pixels = false;
-    // draw pixels line by line
-    for line in 0..sprite_height {
-        // get the address where the sprite line is stored
-        let address = emu.index_register + line;
-        let pixels = emu.ram[address as usize];
-        // Iterate over each pixel in the line
-        for pixel in 0..8 {
-            // Use a mask to fetch current pixel's bit. Only flip if a 1
-            if (pixels & (0b1000_0000 >> pixel)) != 0 {
-                // apply modulo to allow sprites to wrap around screen
-                let x = (screen_x + pixel) as usize % chip::SCREEN_WIDTH;
-                let y = (screen_y + line) as usize % chip::SCREEN_HEIGHT;
+    for plane in 0..2 {
+        let plane_bit = 1 << plane;
+        if emu.planes & plane_bit == 0 {
+            continue;
+        }
+        // draw pixels line by line
+        for line in 0..sprite_height {
+            // get the sprite line, left aligned in 16 bits
+            let pixels = if sprite_width == 16 {
+                emu.read_word(address)
+            } else {
+                (emu.ram[address as usize] as u16) << 8
+            };
+            address = address.wrapping_add(sprite_width as u16 / 8);
+            // Iterate over each pixel in the line
+            for pixel in 0..sprite_width {
+                // Use a mask to fetch current pixel's bit. Only flip if a 1
+                if (pixels & (0x8000 >> pixel)) == 0 {
+                    continue;
+                }
+                let mut x = screen_x + pixel;
+                let mut y = screen_y + line;
+                // sprites either wrap around the screen or are clipped at the edges
+                if emu.quirks.wrap {
+                    x %= width;
+                    y %= height;
+                } else if x >= width || y >= height {
+                    continue;
+                }
                 // Get our pixel's index for our 1D screen array
-                let index = x + chip::SCREEN_WIDTH * y;
+                let index = x + width * y;
                 // Check if we're about to flip the pixel and set
-                flipped_pixels |= emu.frame_buffer[index];
-                emu.frame_buffer[index] ^= true;
+                flipped_pixels |= emu.frame_buffer[index] & plane_bit != 0;
+                emu.frame_buffer[index] ^= plane_bit;
             }
         }
     }
     emu.registers[0xF] = if flipped_pixels {1} else {0};
 
-}
\ No newline at end of file
+    // the original interpreter draws in sync with the display, only one sprite per frame
+    if emu.quirks.vblank && !emu.hires {
+        emu.waiting_for_vblank = true;
+    }
+}
+
+// clears the selected bitplanes
+pub fn clear(emu: &mut chip::Emulation) {
+    let planes = emu.planes;
+    for pixel in emu.frame_buffer.iter_mut() {
+        *pixel &= !planes;
+    }
+}
+
+// scrolls the selected bitplanes by dx, dy pixels, pixels scrolled in are cleared
+pub fn scroll(emu: &mut chip::Emulation, dx: isize, dy: isize) {
+    let width = emu.display_width() as isize;
+    let height = emu.display_height() as isize;
+    let planes = emu.planes;
+    let source = emu.frame_buffer;
+    for y in 0..height {
+        for x in 0..width {
+            let (from_x, from_y) = (x - dx, y - dy);
+            let moved = if from_x >= 0 && from_x < width && from_y >= 0 && from_y < height {
+                source[(from_x + from_y * width) as usize] & planes
+            } else {
+                0
+            };
+            let index = (x + y * width) as usize;
+            emu.frame_buffer[index] = (emu.frame_buffer[index] & !planes) | moved;
+        }
+    }
+}
+
+// switches between the 64x32 and 128x64 display, clearing it
+pub fn set_hires(emu: &mut chip::Emulation, hires: bool) {
+    emu.hires = hires;
+    emu.frame_buffer.fill(0);
+}
@@ -0,0 +1,171 @@
+/*
+    Save states hold the whole machine: CPU, memory, display, timers, platform and quirks.
+    They are all the same size whatever the platform, so frontends can keep them in fixed
+    buffers for rewinding and netplay. A state starts with a header naming the format and the
+    SHA-1 of the ROM it was saved with, and is only loaded back with the same ROM.
+    Breakpoints, watchpoints and cheats belong to the debugger and the player, not the machine,
+    and are left as they are.
+*/
+use std::error::Error;
+use std::fmt;
+
+use crate::chip::{self, Emulation};
+use crate::platform::{Platform, Quirks};
+
+const MAGIC: &[u8; 4] = b"FEO8";
+const VERSION: u8 = 1;
+const HASH_SIZE: usize = 40;
+
+// size of every save state in bytes
+pub const STATE_SIZE: usize = MAGIC.len() + 1 + HASH_SIZE
+    + 1 + Quirks::NAMES.len() + 4                               // platform, quirks, tick rate
+    + 2 + 2 + 2 + chip::STACK_SIZE * 2                          // PC, I, SP and stack
+    + chip::REGISTER_NUM * 2 + 2 + chip::NUM_KEYS               // registers, flags, timers, keys
+    + 4 + 16 + 1 + 1 + 8                                        // display and wait states, audio, random state
+    + chip::HIRES_SCREEN_WIDTH * chip::HIRES_SCREEN_HEIGHT + chip::RAM_SIZE;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateError {
+    InvalidHeader,
+    UnsupportedVersion(u8),
+    WrongRom(String),
+    InvalidSize(usize),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::InvalidHeader => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => write!(f, "unsupported save state version {}", version),
+            StateError::WrongRom(hash) => write!(f, "save state is for another rom ({})", hash),
+            StateError::InvalidSize(size) => write!(f, "save state has the wrong size ({} bytes)", size),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+// reads the fields of a state in the order they were written
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, count: usize) -> &[u8] {
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        bytes
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes(1)[0]
+    }
+
+    fn bool(&mut self) -> bool {
+        self.u8() != 0
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.bytes(2).try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes(4).try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.bytes(8).try_into().unwrap())
+    }
+}
+
+impl Emulation {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(STATE_SIZE);
+        state.extend_from_slice(MAGIC);
+        state.push(VERSION);
+        let mut hash = [0; HASH_SIZE];
+        let rom_hash = self.rom_hash().as_bytes();
+        hash[..rom_hash.len()].copy_from_slice(rom_hash);
+        state.extend_from_slice(&hash);
+
+        let platform = Platform::ALL.iter().position(|platform| *platform == self.platform).unwrap_or_default();
+        state.push(platform as u8);
+        state.extend(Quirks::NAMES.iter().map(|name| self.quirks.get(name).unwrap_or_default() as u8));
+        state.extend_from_slice(&self.ticks_per_frame.to_le_bytes());
+
+        state.extend_from_slice(&self.program_counter.to_le_bytes());
+        state.extend_from_slice(&self.index_register.to_le_bytes());
+        state.extend_from_slice(&self.stack_pointer.to_le_bytes());
+        state.extend(self.stack.iter().flat_map(|address| address.to_le_bytes()));
+        state.extend_from_slice(&self.registers);
+        state.extend_from_slice(&self.flags);
+        state.push(self.delay_timer);
+        state.push(self.sound_timer);
+        state.extend(self.keys.iter().map(|pressed| *pressed as u8));
+
+        state.push(self.hires as u8);
+        state.push(self.planes);
+        state.push(self.waiting_for_vblank as u8);
+        state.push(self.halted as u8);
+        state.extend_from_slice(&self.audio_pattern);
+        state.push(self.pitch);
+        state.push(self.pattern_loaded as u8);
+        state.extend_from_slice(&self.rng_state.to_le_bytes());
+        state.extend_from_slice(&self.frame_buffer);
+        state.extend_from_slice(&self.ram);
+        state
+    }
+
+    // restores a state saved with the loaded rom, nothing changes if it can't be loaded
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        if !state.starts_with(MAGIC) {
+            return Err(StateError::InvalidHeader);
+        }
+        if state.len() != STATE_SIZE {
+            return Err(StateError::InvalidSize(state.len()));
+        }
+        let mut reader = Reader { data: &state[MAGIC.len()..] };
+        let version = reader.u8();
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let hash = String::from_utf8_lossy(reader.bytes(HASH_SIZE)).trim_end_matches('\0').to_string();
+        if hash != self.rom_hash() {
+            return Err(StateError::WrongRom(hash));
+        }
+
+        self.platform = Platform::ALL.get(reader.u8() as usize).copied().unwrap_or_default();
+        for name in Quirks::NAMES {
+            let enabled = reader.bool();
+            self.quirks.set(name, enabled);
+        }
+        self.ticks_per_frame = reader.u32();
+
+        self.program_counter = reader.u16();
+        self.index_register = reader.u16();
+        self.stack_pointer = reader.u16().min(chip::STACK_SIZE as u16);
+        for address in self.stack.iter_mut() {
+            *address = reader.u16();
+        }
+        self.registers.copy_from_slice(reader.bytes(chip::REGISTER_NUM));
+        self.flags.copy_from_slice(reader.bytes(chip::REGISTER_NUM));
+        self.delay_timer = reader.u8();
+        self.sound_timer = reader.u8();
+        for pressed in self.keys.iter_mut() {
+            *pressed = reader.bool();
+        }
+
+        self.hires = reader.bool();
+        self.planes = reader.u8();
+        self.waiting_for_vblank = reader.bool();
+        self.halted = reader.bool();
+        self.fault = None;
+        self.audio_pattern.copy_from_slice(reader.bytes(16));
+        self.pitch = reader.u8();
+        self.pattern_loaded = reader.bool();
+        self.rng_state = reader.u64().max(1);
+        self.frame_buffer.copy_from_slice(reader.bytes(chip::HIRES_SCREEN_WIDTH * chip::HIRES_SCREEN_HEIGHT));
+        self.ram.copy_from_slice(reader.bytes(chip::RAM_SIZE));
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+/*
+    Debug Adapter Protocol server for CHIP-8 programs, for editors like VS Code.
+    The editor starts it and talks to it over stdin and stdout. The launch request takes
+        program      path of the rom
+        sourceMap    optional source map from the assembler, see source_map.rs
+        stopOnEntry  stop before the first instruction
+        platform     platform id, by default the one from the rom database
+        ips          instructions per second
+    Requests are read on their own thread so that the program can be paused while it runs.
+*/
+use std::io;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use session::Session;
+
+mod protocol;
+mod session;
+mod source_map;
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+fn main() {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        loop {
+            match protocol::read_message(&mut stdin) {
+                Ok(Some(message)) => {
+                    if sender.send(message).is_err() {
+                        return;
+                    }
+                },
+                Ok(None) => return,
+                Err(err) => {
+                    eprintln!("Invalid message: {}", err);
+                    return;
+                },
+            }
+        }
+    });
+
+    let mut session = Session::new();
+    let mut next_frame = Instant::now();
+    while !session.finished {
+        if !session.running() {
+            match receiver.recv() {
+                Ok(request) => session.handle(&request),
+                Err(_) => return,
+            }
+            next_frame = Instant::now();
+            continue;
+        }
+        session.run_frame();
+        loop {
+            match receiver.try_recv() {
+                Ok(request) => session.handle(&request),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+        }
+        next_frame += FRAME_TIME;
+        thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+    }
+}
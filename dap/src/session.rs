@@ -0,0 +1,460 @@
+/*
+    A debugging session of one ROM, answering the requests of the editor.
+    The emulation runs frame by frame in real time while the editor waits, checking after every
+    instruction whether a step is done. There is a single thread, the CHIP-8 call stack gives
+    the stack frames and the registers and timers are shown as variables.
+*/
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use feo_core::debug::{Fault, Stop};
+use feo_core::{disasm, Emulation, Platform};
+use serde_json::{json, Value};
+
+use crate::protocol::Output;
+use crate::source_map::SourceMap;
+
+const THREAD_ID: u64 = 1;
+const REGISTERS_REFERENCE: u64 = 1;
+const TIMERS_REFERENCE: u64 = 2;
+// the most instructions a disassemble request gets, as many as fit in the largest memory
+const MAX_INSTRUCTIONS: usize = 0x8000;
+
+// what the running program is heading for
+#[derive(Clone, Copy)]
+enum Goal {
+    Continue,
+    // a step ends at the next instruction, or the next source line when there is a source map.
+    // Stepping over runs calls to the end, returning from the subroutine always ends a step
+    Step { depth: usize, line: Option<u32>, over: bool },
+    StepOut { depth: usize },
+}
+
+pub struct Session {
+    emu: Emulation,
+    output: Output,
+    source_map: Option<SourceMap>,
+    // none while stopped
+    goal: Option<Goal>,
+    stop_on_entry: bool,
+    ips: Option<u32>,
+    // breakpoints of each source file, and those set in the disassembly
+    source_breakpoints: Vec<(PathBuf, Vec<u16>)>,
+    instruction_breakpoints: Vec<u16>,
+    pub finished: bool,
+}
+
+fn format_address(address: u16) -> String {
+    format!("0x{:04X}", address)
+}
+
+// memory references are written in hex with 0x, or in decimal
+fn parse_address(text: &str) -> Option<i64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (index, byte)| value | (*byte as u32) << (16 - 8 * index));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(value >> (18 - 6 * index)) as usize & 0x3F] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn variable(name: &str, value: String, memory_reference: Option<u16>) -> Value {
+    let mut variable = json!({ "name": name, "value": value, "variablesReference": 0 });
+    if let Some(address) = memory_reference {
+        variable["memoryReference"] = json!(format_address(address));
+    }
+    variable
+}
+
+fn byte_value(value: u8) -> String {
+    format!("0x{:02X} ({})", value, value)
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session {
+            emu: Emulation::new(),
+            output: Output::new(),
+            source_map: None,
+            goal: None,
+            stop_on_entry: false,
+            ips: None,
+            source_breakpoints: Vec::new(),
+            instruction_breakpoints: Vec::new(),
+            finished: false,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.goal.is_some()
+    }
+
+    pub fn handle(&mut self, request: &Value) {
+        if request["type"] != "request" {
+            return;
+        }
+        let arguments = &request["arguments"];
+        let result = match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                self.output.response(request, json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsReadMemoryRequest": true,
+                    "supportsDisassembleRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsSteppingGranularity": true,
+                    "supportsTerminateRequest": true,
+                }));
+                self.output.event("initialized", json!({}));
+                return;
+            },
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                self.output.response(request, json!({}));
+                if self.stop_on_entry {
+                    self.stopped("entry");
+                } else {
+                    self.resume(Goal::Continue);
+                }
+                return;
+            },
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(json!({ "scopes": [
+                { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+                { "name": "Timers", "variablesReference": TIMERS_REFERENCE, "expensive": false },
+            ]})),
+            "variables" => Ok(self.variables(arguments["variablesReference"].as_u64().unwrap_or_default())),
+            "readMemory" => self.read_memory(arguments),
+            "disassemble" => self.disassemble(arguments),
+            "continue" => {
+                self.output.response(request, json!({ "allThreadsContinued": true }));
+                self.resume(Goal::Continue);
+                return;
+            },
+            "next" | "stepIn" | "stepOut" => {
+                self.output.response(request, json!({}));
+                let depth = self.emu.stack().len();
+                let line = match arguments["granularity"].as_str() {
+                    Some("instruction") => None,
+                    _ => self.line(self.emu.program_counter()),
+                };
+                self.resume(match request["command"].as_str() {
+                    Some("next") => Goal::Step { depth, line, over: true },
+                    Some("stepIn") => Goal::Step { depth, line, over: false },
+                    _ => Goal::StepOut { depth },
+                });
+                return;
+            },
+            "pause" => {
+                self.output.response(request, json!({}));
+                if self.running() {
+                    self.stopped("pause");
+                }
+                return;
+            },
+            "disconnect" | "terminate" => {
+                self.output.response(request, json!({}));
+                self.output.event("terminated", json!({}));
+                self.finished = true;
+                return;
+            },
+            command => Err(format!("Unsupported request '{}'", command)),
+        };
+        match result {
+            Ok(body) => self.output.response(request, body),
+            Err(err) => self.output.error(request, &err),
+        }
+    }
+
+    fn launch(&mut self, arguments: &Value) -> Result<Value, String> {
+        let program = arguments["program"].as_str().ok_or("launch needs the path of a rom as 'program'")?;
+        let buffer = fs::read(program).map_err(|err| format!("Unable to open {}: {}", program, err))?;
+        if buffer.len() > feo_core::MAX_ROM_SIZE {
+            return Err(format!("{} is too large to be a rom ({} bytes)", program, buffer.len()));
+        }
+        if let Some(path) = arguments["sourceMap"].as_str() {
+            self.source_map = Some(SourceMap::load(Path::new(path))?);
+        }
+        self.emu.reset();
+        self.emu.load(&buffer);
+        if let Some(id) = arguments["platform"].as_str() {
+            let platform = Platform::from_id(id).ok_or(format!("Unknown platform '{}'", id))?;
+            self.emu.set_platform(platform);
+        }
+        self.ips = arguments["ips"].as_u64().map(|ips| ips.max(1) as u32);
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+        let name = match self.emu.rom_info() {
+            Some(info) => info.title.clone(),
+            None => program.to_string(),
+        };
+        self.output.event("output", json!({
+            "category": "console",
+            "output": format!("Running {} as {}\n", name, self.emu.platform().id()),
+        }));
+        Ok(json!({}))
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = PathBuf::from(arguments["source"]["path"].as_str().ok_or("breakpoints need a source path")?);
+        let mut addresses = Vec::new();
+        let mut breakpoints = Vec::new();
+        for requested in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let line = requested["line"].as_u64().unwrap_or_default() as u32;
+            let found = self.source_map.as_ref().and_then(|map| map.address(&path, line));
+            breakpoints.push(match (found, &self.source_map) {
+                (Some((address, line)), _) => {
+                    addresses.push(address);
+                    json!({ "verified": true, "line": line, "instructionReference": format_address(address) })
+                },
+                (None, Some(_)) => json!({ "verified": false, "line": line, "message": "No code at this line" }),
+                (None, None) => json!({ "verified": false, "line": line, "message": "Launch with a source map to set breakpoints in source files" }),
+            });
+        }
+        self.source_breakpoints.retain(|(known, _)| *known != path);
+        self.source_breakpoints.push((path, addresses));
+        self.update_breakpoints();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let mut breakpoints = Vec::new();
+        self.instruction_breakpoints.clear();
+        for requested in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let address = requested["instructionReference"].as_str().and_then(parse_address).unwrap_or(-1)
+                + requested["offset"].as_i64().unwrap_or_default();
+            match u16::try_from(address) {
+                Ok(address) if (address as usize) < self.emu.memory_size() => {
+                    self.instruction_breakpoints.push(address);
+                    breakpoints.push(json!({ "verified": true, "instructionReference": format_address(address) }));
+                },
+                _ => breakpoints.push(json!({ "verified": false, "message": "Not an address in memory" })),
+            }
+        }
+        self.update_breakpoints();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    // the emulation keeps one set of breakpoints, made from those of all sources
+    fn update_breakpoints(&mut self) {
+        let current: Vec<u16> = self.emu.breakpoints().iter().copied().collect();
+        for address in current {
+            self.emu.set_breakpoint(address, false);
+        }
+        let sources = self.source_breakpoints.iter().flat_map(|(_, addresses)| addresses);
+        for address in sources.chain(&self.instruction_breakpoints) {
+            self.emu.set_breakpoint(*address, true);
+        }
+    }
+
+    fn line(&self, address: u16) -> Option<u32> {
+        self.source_map.as_ref()?.location(address).map(|(_, line)| line)
+    }
+
+    // name of the subroutine called by the CALL before a return address
+    fn callee(&self, return_address: u16) -> String {
+        let memory = self.emu.memory();
+        let call = return_address.wrapping_sub(2) as usize;
+        match memory.get(call..call + 2) {
+            Some([high, low]) if high >> 4 == 2 => format!("sub_{:03X}", (*high as u16 & 0xF) << 8 | *low as u16),
+            _ => String::from("subroutine"),
+        }
+    }
+
+    // the innermost frame is at PC, the others at the CALL that entered the next one
+    fn stack_trace(&self) -> Value {
+        let stack = self.emu.stack();
+        let mut frames = Vec::new();
+        let mut address = self.emu.program_counter();
+        for depth in (0..=stack.len()).rev() {
+            let name = match depth {
+                0 => String::from("main"),
+                _ => self.callee(stack[depth - 1]),
+            };
+            let mut frame = json!({
+                "id": frames.len() + 1,
+                "name": name,
+                "line": 0,
+                "column": 0,
+                "instructionPointerReference": format_address(address),
+            });
+            if let Some((path, line)) = self.source_map.as_ref().and_then(|map| map.location(address)) {
+                frame["source"] = json!({
+                    "name": path.file_name().unwrap_or_default().to_string_lossy(),
+                    "path": path.to_string_lossy(),
+                });
+                frame["line"] = json!(line);
+                frame["column"] = json!(1);
+            }
+            frames.push(frame);
+            if depth > 0 {
+                address = stack[depth - 1].wrapping_sub(2);
+            }
+        }
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn variables(&self, reference: u64) -> Value {
+        let emu = &self.emu;
+        let variables = match reference {
+            REGISTERS_REFERENCE => {
+                let mut variables: Vec<Value> = emu
+                    .registers()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| variable(&format!("V{:X}", index), byte_value(*value), None))
+                    .collect();
+                variables.push(variable("I", format_address(emu.index_register()), Some(emu.index_register())));
+                variables.push(variable("PC", format_address(emu.program_counter()), Some(emu.program_counter())));
+                variables.push(variable("SP", emu.stack_pointer().to_string(), None));
+                variables
+            },
+            TIMERS_REFERENCE => vec![
+                variable("DT", byte_value(emu.delay_timer()), None),
+                variable("ST", byte_value(emu.sound_timer()), None),
+            ],
+            _ => Vec::new(),
+        };
+        json!({ "variables": variables })
+    }
+
+    fn read_memory(&self, arguments: &Value) -> Result<Value, String> {
+        let reference = arguments["memoryReference"].as_str().and_then(parse_address).ok_or("Invalid memory reference")?;
+        let start = reference.saturating_add(arguments["offset"].as_i64().unwrap_or_default());
+        let count = arguments["count"].as_u64().unwrap_or_default() as usize;
+        let memory = self.emu.memory();
+        // reads outside of memory come back empty
+        let data = match usize::try_from(start) {
+            Ok(start) => {
+                let end = start.saturating_add(count).min(memory.len());
+                &memory[start.min(end)..end]
+            },
+            Err(_) => &[],
+        };
+        Ok(json!({
+            "address": format!("0x{:04X}", start),
+            "data": base64(data),
+            "unreadableBytes": count - data.len(),
+        }))
+    }
+
+    // instructions are counted as two bytes before the reference, as they can't be decoded backwards
+    fn disassemble(&self, arguments: &Value) -> Result<Value, String> {
+        let reference = arguments["memoryReference"].as_str().and_then(parse_address).ok_or("Invalid memory reference")?;
+        let mut address = reference
+            .saturating_add(arguments["offset"].as_i64().unwrap_or_default())
+            .saturating_add(arguments["instructionOffset"].as_i64().unwrap_or_default().saturating_mul(2));
+        let count = (arguments["instructionCount"].as_u64().unwrap_or_default() as usize).min(MAX_INSTRUCTIONS);
+        let mut instructions = Vec::with_capacity(count);
+        while instructions.len() < count {
+            let Some(line) = u16::try_from(address)
+                .ok()
+                .filter(|address| (*address as usize) < self.emu.memory_size())
+                .and_then(|address| disasm::disassemble(&self.emu, address, 1).pop())
+            else {
+                instructions.push(json!({ "address": format!("0x{:04X}", address), "instruction": "??", "presentationHint": "invalid" }));
+                address = address.saturating_add(2);
+                continue;
+            };
+            let mut instruction = json!({
+                "address": format_address(line.address),
+                "instructionBytes": format!("{:04X}", line.opcode),
+                "instruction": line.text,
+            });
+            if let Some((path, source_line)) = self.source_map.as_ref().and_then(|map| map.location(line.address)) {
+                instruction["location"] = json!({ "path": path.to_string_lossy() });
+                instruction["line"] = json!(source_line);
+            }
+            instructions.push(instruction);
+            address += line.size as i64;
+        }
+        Ok(json!({ "instructions": instructions }))
+    }
+
+    fn resume(&mut self, goal: Goal) {
+        // the first instruction runs even with a breakpoint on it, or the program would never leave it
+        if self.emu.breakpoints().contains(&self.emu.program_counter()) || !matches!(goal, Goal::Continue) {
+            self.emu.step();
+            if self.reached(goal) {
+                self.stopped("step");
+                return;
+            }
+        }
+        self.goal = Some(goal);
+    }
+
+    fn stopped(&mut self, reason: &str) {
+        self.goal = None;
+        self.output.event("stopped", json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }));
+    }
+
+    fn faulted(&mut self, fault: Fault) {
+        self.goal = None;
+        let description = fault.to_string();
+        self.output.event("stopped", json!({ "reason": "exception", "description": description, "text": description, "threadId": THREAD_ID, "allThreadsStopped": true }));
+    }
+
+    fn exited(&mut self) {
+        self.goal = None;
+        self.output.event("exited", json!({ "exitCode": 0 }));
+        self.output.event("terminated", json!({}));
+    }
+
+    fn reached(&self, goal: Goal) -> bool {
+        let depth = self.emu.stack().len();
+        match goal {
+            Goal::Continue => false,
+            Goal::StepOut { depth: start } => depth < start,
+            Goal::Step { depth: start, line, over } => {
+                if depth < start {
+                    return true;
+                }
+                if over && depth > start {
+                    return false;
+                }
+                match line {
+                    // instructions without a line are passed over
+                    Some(start_line) => self.line(self.emu.program_counter()).is_some_and(|line| line != start_line),
+                    None => true,
+                }
+            },
+        }
+    }
+
+    // runs one frame of the program, stopping where the goal is reached
+    pub fn run_frame(&mut self) {
+        let Some(goal) = self.goal else {
+            return;
+        };
+        let ticks = self.ips.map_or(self.emu.ticks_per_frame(), |ips| (ips / 60).max(1));
+        for _ in 0..ticks {
+            // a program that faulted before stops again straight away
+            match self.emu.run(1).or(self.emu.fault().map(Stop::Fault)) {
+                Some(Stop::Breakpoint(_)) => return self.stopped("breakpoint"),
+                Some(Stop::Watchpoint(_)) => return self.stopped("data breakpoint"),
+                Some(Stop::Fault(fault)) => return self.faulted(fault),
+                // a program that ends itself with EXIT ends the session
+                None if self.emu.exited() => return self.exited(),
+                None if self.reached(goal) => return self.stopped("step"),
+                None => (),
+            }
+        }
+        self.emu.tick_timers();
+    }
+}
@@ -0,0 +1,68 @@
+/*
+    Source maps tie ROM addresses to the assembly source they came from.
+    The file has one instruction per line, its address in hex followed by the source file and line:
+        0x200 game.8o:12
+        0x202 game.8o:13
+    Source paths are relative to the source map. Lines starting with # are comments.
+*/
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct SourceMap {
+    files: Vec<PathBuf>,
+    // address to file index and line
+    lines: BTreeMap<u16, (usize, u32)>,
+}
+
+// paths are compared once both are made absolute, so "./game.8o" and the full path match
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl SourceMap {
+    pub fn load(path: &Path) -> Result<SourceMap, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("Unable to open {}: {}", path.display(), err))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut map = SourceMap { files: Vec::new(), lines: BTreeMap::new() };
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || format!("{}:{}: expected an address followed by file:line", path.display(), number + 1);
+            let (address, location) = line.split_once(char::is_whitespace).ok_or_else(error)?;
+            let address = u16::from_str_radix(address.trim_start_matches("0x"), 16).map_err(|_| error())?;
+            // Windows paths contain colons, the line number follows the last one
+            let (file, source_line) = location.trim().rsplit_once(':').ok_or_else(error)?;
+            let source_line = source_line.parse().map_err(|_| error())?;
+            let file = normalize(&dir.join(file));
+            let index = match map.files.iter().position(|known| *known == file) {
+                Some(index) => index,
+                None => {
+                    map.files.push(file);
+                    map.files.len() - 1
+                },
+            };
+            map.lines.insert(address, (index, source_line));
+        }
+        Ok(map)
+    }
+
+    // source file and line of the instruction at the address
+    pub fn location(&self, address: u16) -> Option<(&Path, u32)> {
+        self.lines.get(&address).map(|(file, line)| (self.files[*file].as_path(), *line))
+    }
+
+    // first address of a source line, or of the next line with code on it.
+    // Returns the address and the line it belongs to
+    pub fn address(&self, path: &Path, line: u32) -> Option<(u16, u32)> {
+        let path = normalize(path);
+        let file = self.files.iter().position(|known| *known == path)?;
+        self.lines
+            .iter()
+            .filter(|(_, (index, source_line))| *index == file && *source_line >= line)
+            .min_by_key(|(address, (_, source_line))| (*source_line, **address))
+            .map(|(address, (_, source_line))| (*address, *source_line))
+    }
+}
@@ -0,0 +1,173 @@
+/*
+    Rom picker shown when no rom is given on the command line, or when opened with Ctrl+O.
+    Lists the files and folders of a directory, Enter opens the selected one
+    and typing a letter jumps to the next entry starting with it.
+*/
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::text;
+
+struct Entry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+pub struct Browser {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    scroll: usize,
+    error: Option<String>,
+}
+
+impl Browser {
+    pub fn new(dir: &Path) -> Self {
+        let mut browser = Browser { dir: PathBuf::new(), entries: Vec::new(), selected: 0, scroll: 0, error: None };
+        browser.open_dir(dir);
+        browser
+    }
+
+    // lists folders first, then files, hidden ones are left out
+    fn open_dir(&mut self, dir: &Path) {
+        self.dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        self.entries.clear();
+        self.selected = 0;
+        self.scroll = 0;
+        self.error = None;
+        if let Some(parent) = self.dir.parent() {
+            self.entries.push(Entry { name: String::from(".."), path: parent.to_path_buf(), is_dir: true });
+        }
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                self.error = Some(err.to_string());
+                return;
+            },
+        };
+        let mut entries: Vec<Entry> = read_dir
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| Entry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                path: entry.path(),
+                is_dir: entry.path().is_dir(),
+            })
+            .collect();
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+        self.entries.extend(entries);
+        // start on the first file rather than on ..
+        self.selected = self.entries.iter().position(|entry| !entry.is_dir).unwrap_or(0);
+    }
+
+    // shown instead of the file list, e.g. when the selected rom can't be read
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    // moves the selection, returns the rom file to open when one is chosen
+    pub fn handle_key(&mut self, key: Keycode, page: usize) -> Option<PathBuf> {
+        let last = self.entries.len().saturating_sub(1);
+        match key {
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => self.selected = (self.selected + 1).min(last),
+            Keycode::PageUp => self.selected = self.selected.saturating_sub(page),
+            Keycode::PageDown => self.selected = (self.selected + page).min(last),
+            Keycode::Home => self.selected = 0,
+            Keycode::End => self.selected = last,
+            Keycode::Backspace | Keycode::Left => {
+                if let Some(parent) = self.dir.parent().map(Path::to_path_buf) {
+                    self.open_dir(&parent);
+                }
+            },
+            Keycode::Return | Keycode::KpEnter | Keycode::Right => {
+                let entry = self.entries.get(self.selected)?;
+                if entry.is_dir {
+                    let dir = entry.path.clone();
+                    self.open_dir(&dir);
+                } else {
+                    return Some(entry.path.clone());
+                }
+            },
+            _ => {
+                // jump to the next entry starting with the typed character
+                let name = key.name().to_lowercase();
+                if name.chars().count() == 1 {
+                    let count = self.entries.len();
+                    let next = (1..=count)
+                        .map(|offset| (self.selected + offset) % count)
+                        .find(|&i| self.entries[i].name.to_lowercase().starts_with(&name));
+                    if let Some(next) = next {
+                        self.selected = next;
+                    }
+                }
+            },
+        }
+        None
+    }
+
+    // font pixel size and number of list lines that fit the window
+    pub fn layout(canvas: &Canvas<Window>) -> (u32, usize) {
+        let (_, height) = canvas.output_size().unwrap();
+        let size = (height / 120).max(1);
+        let lines = (height / (text::CHAR_HEIGHT * size)).saturating_sub(3).max(1);
+        (size, lines as usize)
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, background: Color, foreground: Color) {
+        let (size, lines) = Browser::layout(canvas);
+        let (width, _) = canvas.output_size().unwrap();
+        let line_height = (text::CHAR_HEIGHT * size) as i32;
+        let margin = (text::CHAR_WIDTH * size) as i32;
+        canvas.set_draw_color(background);
+        canvas.clear();
+
+        // keep the selection on screen
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + lines {
+            self.scroll = self.selected + 1 - lines;
+        }
+
+        let max_chars = ((width as i32 - 2 * margin) / margin).max(1) as usize;
+        text::draw(canvas, &fit(&self.dir.to_string_lossy(), max_chars), margin, margin / 2, size, foreground);
+        let top = margin / 2 + 2 * line_height;
+        if let Some(error) = &self.error {
+            text::draw(canvas, &fit(error, max_chars), margin, top, size, foreground);
+            return;
+        }
+        let visible = self.entries.iter().enumerate().skip(self.scroll).take(lines);
+        for (line, (i, entry)) in visible.enumerate() {
+            let y = top + line as i32 * line_height;
+            let name = if entry.is_dir { format!("{}/", entry.name) } else { entry.name.clone() };
+            let name = fit(&name, max_chars);
+            let color = if i == self.selected {
+                // the selected entry is drawn inverted
+                canvas.set_draw_color(foreground);
+                let bar = Rect::new(margin - size as i32, y - size as i32, text::width(&name, size) + size, line_height as u32);
+                canvas.fill_rect(bar).unwrap();
+                background
+            } else {
+                foreground
+            };
+            text::draw(canvas, &name, margin, y, size, color);
+        }
+    }
+}
+
+// shortens text to at most max characters, keeping the end which is the interesting part of a path
+fn fit(text: &str, max: usize) -> String {
+    let count = text.chars().count();
+    if count <= max {
+        return text.to_string();
+    }
+    let keep = max.saturating_sub(2);
+    format!("..{}", text.chars().skip(count - keep).collect::<String>())
+}
@@ -0,0 +1,170 @@
+/*
+    Screenshots and GIF recordings of the display, as shown with the current palette and filter.
+    Files are named after the time they were taken and go to the captures folder
+    set in the config file.
+    GIFs count time in hundredths of a second, so frame delays alternate between 1 and 2
+    to keep 60 frames per second, and frames that don't change only lengthen the previous one.
+*/
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use feo_core::filter::Filter;
+use feo_core::palette::Palette;
+
+// a frame at the resolution of the display
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    fn capture(filter: &Filter, palette: &Palette) -> Image {
+        Image { width: filter.width(), height: filter.height(), pixels: filter.pixels(palette).collect() }
+    }
+}
+
+// makes every pixel of an image scale x scale pixels large
+fn scaled<T: Copy>(pixels: &[T], width: usize, scale: usize) -> Vec<T> {
+    let mut scaled = Vec::with_capacity(pixels.len() * scale * scale);
+    for row in pixels.chunks(width) {
+        for _ in 0..scale {
+            for pixel in row {
+                scaled.extend(std::iter::repeat_n(*pixel, scale));
+            }
+        }
+    }
+    scaled
+}
+
+// e.g. captures/feo8-20240131-154502.png
+fn file_name(dir: &Path, extension: &str) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|err| format!("Unable to create {}: {}", dir.display(), err))?;
+    let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut path = dir.join(format!("feo8-{}.{}", time, extension));
+    // several captures in the same second get a number
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = dir.join(format!("feo8-{}-{}.{}", time, count, extension));
+    }
+    Ok(path)
+}
+
+// saves the display as a PNG, each pixel scale x scale pixels large
+pub fn screenshot(dir: &Path, filter: &Filter, palette: &Palette, scale: usize) -> Result<PathBuf, String> {
+    let image = Image::capture(filter, palette);
+    if image.width == 0 {
+        return Err(String::from("Nothing to capture yet"));
+    }
+    let path = file_name(dir, "png")?;
+    let error = |err: &dyn std::fmt::Display| format!("Unable to save {}: {}", path.display(), err);
+    let file = File::create(&path).map_err(|err| error(&err))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), (image.width * scale) as u32, (image.height * scale) as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| error(&err))?;
+    writer.write_image_data(scaled(&image.pixels, image.width, scale).as_flattened()).map_err(|err| error(&err))?;
+    Ok(path)
+}
+
+pub struct Recording {
+    path: PathBuf,
+    encoder: gif::Encoder<BufWriter<File>>,
+    width: usize,
+    // the last frame, written once a different one comes or the recording stops
+    pending: Option<Image>,
+    pending_frames: u32,
+    // frames and hundredths of a second written so far
+    frames: u32,
+    written: u32,
+}
+
+impl Recording {
+    // records at the given scale of the 64x32 display, rounded to an even number
+    // so that the 128x64 display fills the same size
+    pub fn start(dir: &Path, scale: usize) -> Result<Recording, String> {
+        let scale = (scale & !1).max(2);
+        let width = feo_core::SCREEN_WIDTH * scale;
+        let height = feo_core::SCREEN_HEIGHT * scale;
+        let path = file_name(dir, "gif")?;
+        let error = |err: &dyn std::fmt::Display| format!("Unable to record {}: {}", path.display(), err);
+        let file = File::create(&path).map_err(|err| error(&err))?;
+        let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[]).map_err(|err| error(&err))?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(|err| error(&err))?;
+        Ok(Recording { path, encoder, width, pending: None, pending_frames: 0, frames: 0, written: 0 })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // adds a frame, to be called once for every emulated frame
+    pub fn add_frame(&mut self, filter: &Filter, palette: &Palette) -> Result<(), String> {
+        let image = Image::capture(filter, palette);
+        if image.width == 0 {
+            return Ok(());
+        }
+        if let Some(pending) = &self.pending {
+            if pending.width == image.width && pending.pixels == image.pixels {
+                self.pending_frames += 1;
+                return Ok(());
+            }
+        }
+        self.write_pending()?;
+        self.pending = Some(image);
+        self.pending_frames = 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf, String> {
+        self.write_pending()?;
+        Ok(self.path)
+    }
+
+    fn write_pending(&mut self) -> Result<(), String> {
+        let Some(image) = self.pending.take() else {
+            return Ok(());
+        };
+        // the delay that brings the total closest to the frames at 60 per second
+        self.frames += self.pending_frames;
+        let delay = (self.frames * 100 + 30) / 60 - self.written;
+        self.written += delay;
+
+        let scale = (self.width / image.width).max(1);
+        let (width, height) = ((image.width * scale) as u16, (image.height * scale) as u16);
+        let mut frame = match index_colors(&image.pixels) {
+            Some((indices, palette)) => {
+                gif::Frame::from_palette_pixels(width, height, scaled(&indices, image.width, scale), palette, None)
+            },
+            // too many colours for a palette of 256, let the encoder pick them
+            None => gif::Frame::from_rgb(width, height, scaled(&image.pixels, image.width, scale).as_flattened()),
+        };
+        frame.delay = delay as u16;
+        self.encoder
+            .write_frame(&frame)
+            .map_err(|err| format!("Unable to record {}: {}", self.path.display(), err))
+    }
+}
+
+// palette indices of the pixels, when they use no more than 256 colours
+fn index_colors(pixels: &[[u8; 3]]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut indices = Vec::with_capacity(pixels.len());
+    let mut colors: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    for pixel in pixels {
+        let index = match colors.get(pixel) {
+            Some(index) => *index,
+            None => {
+                let index = u8::try_from(colors.len()).ok()?;
+                colors.insert(*pixel, index);
+                palette.extend_from_slice(pixel);
+                index
+            },
+        };
+        indices.push(index);
+    }
+    Some((indices, palette))
+}
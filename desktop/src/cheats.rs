@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::PathBuf;
+
+use feo_core::cheats;
+use feo_core::Emulation;
+
+use crate::config::Config;
+
+const CHEAT_DIR: &str = "cheats";
+
+// cheats are stored per rom as cheats/<sha1>.cht in the folder of the config file
+fn cheat_dir() -> Option<PathBuf> {
+    Config::path().and_then(|path| path.parent().map(|dir| dir.join(CHEAT_DIR)))
+}
+
+fn cheat_path(emu: &Emulation) -> Option<PathBuf> {
+    cheat_dir().map(|dir| dir.join(format!("{}.cht", emu.rom_hash())))
+}
+
+// loads the cheat file of the current rom, if there is one
+pub fn load(emu: &mut Emulation) {
+    let Some(path) = cheat_path(emu) else {
+        return;
+    };
+    let Ok(text) = fs::read_to_string(&path) else {
+        return;
+    };
+    match cheats::from_text(emu.rom_hash(), &text) {
+        Ok(loaded) => {
+            println!("Loaded {} cheat(s) from {}", loaded.len(), path.display());
+            *emu.cheats_mut() = loaded;
+            print_cheats(emu);
+        },
+        Err(err) => eprintln!("Unable to load cheats from {}: {}", path.display(), err),
+    }
+}
+
+// writes the cheats back so their on/off state is kept between runs
+pub fn save(emu: &Emulation) {
+    if emu.cheats().is_empty() {
+        return;
+    }
+    let (Some(dir), Some(path)) = (cheat_dir(), cheat_path(emu)) else {
+        return;
+    };
+    let text = cheats::to_text(emu.rom_hash(), emu.cheats());
+    if let Err(err) = fs::create_dir_all(dir).and_then(|_| fs::write(&path, text)) {
+        eprintln!("Unable to save cheats to {}: {}", path.display(), err);
+    }
+}
+
+// toggles a single cheat by its position in the list
+pub fn toggle(emu: &mut Emulation, index: usize) {
+    if let Some(cheat) = emu.cheats_mut().get_mut(index) {
+        cheat.enabled = !cheat.enabled;
+        print_cheats(emu);
+    }
+}
+
+// turns all cheats off if any is on, otherwise turns them all on
+pub fn toggle_all(emu: &mut Emulation) {
+    let enable = !emu.cheats().iter().any(|cheat| cheat.enabled);
+    for cheat in emu.cheats_mut() {
+        cheat.enabled = enable;
+    }
+    print_cheats(emu);
+}
+
+fn print_cheats(emu: &Emulation) {
+    for (i, cheat) in emu.cheats().iter().enumerate() {
+        let state = if cheat.enabled { "on" } else { "off" };
+        println!("  [{}] {:<3} {:#05x} = {:02x} {}", i + 1, state, cheat.address, cheat.value, cheat.name);
+    }
+}
@@ -0,0 +1,331 @@
+/*
+    User configuration, read from config.toml in the feo8 folder of the user's config directory
+    (~/.config/feo8 on Linux, %APPDATA%\feo8 on Windows).
+    A commented default file is written there on the first start.
+    Problems are printed and the defaults used for the affected settings.
+*/
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use feo_core::palette::Palette;
+use feo_core::Emulation;
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use serde::Deserialize;
+
+use crate::controller::ControllerMap;
+use crate::keymap::Keymap;
+use crate::options;
+
+const CONFIG_FILE: &str = "config.toml";
+const DEFAULT_ROM_DIR: &str = "test/roms";
+const DEFAULT_CAPTURES_DIR: &str = "captures";
+
+const DEFAULT_CONFIG: &str = r#"# FeO8 configuration
+
+# Folder listed by the rom browser when no rom is given on the command line.
+rom_dir = "test/roms"
+# Folder for screenshots (F12) and GIF recordings (Shift+F12).
+captures_dir = "captures"
+
+# Colour palettes added to the built-in themes (feo8, classic, green, amber, contrast, octo),
+# F3 switches between them. Colours are the background and the foreground, optionally followed
+# by the colours of XO-CHIP's second bitplane and of pixels set in both planes.
+[palettes]
+# gameboy = ["0f380f", "9bbc0f", "306230", "8bac0f"]
+
+# Maps keyboard keys to the 16 keys of the hex keypad.
+# Each hex key takes one SDL key name or a list of them, e.g. "Q", "Left", "Keypad 8", "Space".
+[keymap]
+1 = "1"
+2 = "2"
+3 = "3"
+C = "4"
+4 = "Q"
+5 = "W"
+6 = "E"
+D = "R"
+7 = "A"
+8 = "S"
+9 = "D"
+E = "F"
+A = "Z"
+0 = "X"
+B = "C"
+F = "V"
+
+# Maps game controller buttons to hex keys, the left stick follows the D-pad.
+# Buttons: a, b, x, y, back, guide, start, leftstick, rightstick, leftshoulder, rightshoulder,
+# dpup, dpdown, dpleft, dpright. By default the D-pad and a/b use the rom database's key hints.
+[controller]
+deadzone = 8000
+# dpup = "5"
+# dpleft = "7"
+# dpdown = "8"
+# dpright = "9"
+# a = "6"
+# b = "4"
+
+# Keys and buttons can be changed for a single rom, by its file name or SHA-1 hash.
+# Only the hex keys and buttons listed are changed.
+# [rom."INVADERS".keymap]
+# 4 = ["Left", "Q"]
+# 5 = ["Space", "W"]
+# 6 = ["Right", "E"]
+# [rom."INVADERS".controller]
+# a = "5"
+"#;
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    rom_dir: Option<PathBuf>,
+    captures_dir: Option<PathBuf>,
+    keymap: Option<BTreeMap<String, KeyNames>>,
+    controller: Option<BTreeMap<String, toml::Value>>,
+    #[serde(default)]
+    palettes: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    rom: BTreeMap<String, RomConfigFile>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RomConfigFile {
+    keymap: Option<BTreeMap<String, KeyNames>>,
+    controller: Option<BTreeMap<String, toml::Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+// hex keys with the keyboard keys mapped to them
+type KeyBindings = Vec<(usize, Vec<Keycode>)>;
+
+#[derive(Default)]
+struct ControllerBindings {
+    buttons: Vec<(Button, usize)>,
+    deadzone: Option<i16>,
+}
+
+#[derive(Default)]
+struct RomConfig {
+    keymap: Option<KeyBindings>,
+    controller: Option<ControllerBindings>,
+}
+
+pub struct Config {
+    pub rom_dir: PathBuf,
+    pub captures_dir: PathBuf,
+    // custom palettes by name
+    pub palettes: Vec<(String, Palette)>,
+    keymap: Keymap,
+    controller: ControllerBindings,
+    roms: BTreeMap<String, RomConfig>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("feo8").join(CONFIG_FILE))
+    }
+
+    // reads the config file, printing any problems with it
+    pub fn load() -> Config {
+        let mut config = Config {
+            rom_dir: PathBuf::from(DEFAULT_ROM_DIR),
+            captures_dir: PathBuf::from(DEFAULT_CAPTURES_DIR),
+            palettes: Vec::new(),
+            keymap: Keymap::qwerty(),
+            controller: ControllerBindings::default(),
+            roms: BTreeMap::new(),
+        };
+        let Some(path) = Config::path() else {
+            return config;
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                write_default(&path);
+                return config;
+            },
+        };
+        let file: ConfigFile = match toml::from_str(&text) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                return config;
+            },
+        };
+
+        if let Some(rom_dir) = file.rom_dir {
+            config.rom_dir = rom_dir;
+        }
+        if let Some(captures_dir) = file.captures_dir {
+            config.captures_dir = captures_dir;
+        }
+        let mut errors = Vec::new();
+        if let Some(keymap) = &file.keymap {
+            match parse_bindings(keymap) {
+                Ok(bindings) => {
+                    config.keymap = Keymap::default();
+                    apply_bindings(&mut config.keymap, &bindings);
+                },
+                Err(err) => errors.extend(err.into_iter().map(|err| format!("[keymap] {}", err))),
+            }
+        }
+        if let Some(controller) = &file.controller {
+            match parse_controller(controller) {
+                Ok(bindings) => config.controller = bindings,
+                Err(err) => errors.extend(err.into_iter().map(|err| format!("[controller] {}", err))),
+            }
+        }
+        for (name, colors) in &file.palettes {
+            match parse_palette(colors) {
+                Ok(palette) => config.palettes.push((name.clone(), palette)),
+                Err(err) => errors.push(format!("[palettes] {}: {}", name, err)),
+            }
+        }
+        for (rom, rom_file) in &file.rom {
+            let mut rom_config = RomConfig::default();
+            if let Some(keymap) = &rom_file.keymap {
+                match parse_bindings(keymap) {
+                    Ok(bindings) => rom_config.keymap = Some(bindings),
+                    Err(err) => errors.extend(err.into_iter().map(|err| format!("[rom.\"{}\".keymap] {}", rom, err))),
+                }
+            }
+            if let Some(controller) = &rom_file.controller {
+                match parse_controller(controller) {
+                    Ok(bindings) => rom_config.controller = Some(bindings),
+                    Err(err) => errors.extend(err.into_iter().map(|err| format!("[rom.\"{}\".controller] {}", rom, err))),
+                }
+            }
+            config.roms.insert(rom.to_lowercase(), rom_config);
+        }
+        for error in errors {
+            eprintln!("{}: {}", path.display(), error);
+        }
+        config
+    }
+
+    // settings of a rom, by hash or file name
+    fn rom(&self, rom: &Path, hash: &str) -> Option<&RomConfig> {
+        let file_name = rom.file_name().map(|name| name.to_string_lossy().to_lowercase());
+        self.roms.get(hash).or_else(|| file_name.and_then(|name| self.roms.get(&name)))
+    }
+
+    // keymap for a rom, with the rom's own bindings replacing those of the hex keys they list
+    pub fn keymap(&self, rom: &Path, hash: &str) -> Keymap {
+        let mut keymap = self.keymap.clone();
+        if let Some(bindings) = self.rom(rom, hash).and_then(|config| config.keymap.as_ref()) {
+            apply_bindings(&mut keymap, bindings);
+        }
+        keymap
+    }
+
+    // controller maps for the first and second player, the configured buttons apply to the first
+    pub fn controller_maps(&self, rom: &Path, emu: &Emulation) -> Vec<ControllerMap> {
+        let mut maps = vec![ControllerMap::for_rom(emu, 0), ControllerMap::for_rom(emu, 1)];
+        let rom_bindings = self.rom(rom, emu.rom_hash()).and_then(|config| config.controller.as_ref());
+        for bindings in std::iter::once(&self.controller).chain(rom_bindings) {
+            maps[0].buttons.extend(bindings.buttons.iter().copied());
+            if let Some(deadzone) = bindings.deadzone {
+                for map in maps.iter_mut() {
+                    map.deadzone = deadzone;
+                }
+            }
+        }
+        maps
+    }
+}
+
+fn apply_bindings(keymap: &mut Keymap, bindings: &KeyBindings) {
+    for (hex_key, keys) in bindings {
+        keymap.unbind(*hex_key);
+        for key in keys {
+            keymap.bind(*key, *hex_key);
+        }
+    }
+}
+
+// checks the hex keys and key names, and that no key is mapped twice
+fn parse_bindings(table: &BTreeMap<String, KeyNames>) -> Result<KeyBindings, Vec<String>> {
+    let mut bindings = Vec::new();
+    let mut errors = Vec::new();
+    let mut used: BTreeMap<String, String> = BTreeMap::new();
+    for (hex_key, names) in table {
+        let hex = match usize::from_str_radix(hex_key, 16) {
+            Ok(hex) if hex_key.len() == 1 => hex,
+            _ => {
+                errors.push(format!("'{}' is not a hex key, use 0-9 or A-F", hex_key));
+                continue;
+            },
+        };
+        let names = match names {
+            KeyNames::One(name) => std::slice::from_ref(name),
+            KeyNames::Many(names) => names.as_slice(),
+        };
+        let mut keys = Vec::new();
+        for name in names {
+            match Keycode::from_name(name) {
+                Some(key) => keys.push(key),
+                None => errors.push(format!("{}: unknown key name '{}'", hex_key, name)),
+            }
+            if let Some(other) = used.insert(name.to_lowercase(), hex_key.clone()) {
+                errors.push(format!("'{}' is mapped to both {} and {}", name, other, hex_key));
+            }
+        }
+        bindings.push((hex, keys));
+    }
+    if errors.is_empty() { Ok(bindings) } else { Err(errors) }
+}
+
+// button names map to a hex key each, and the deadzone is a number
+fn parse_controller(table: &BTreeMap<String, toml::Value>) -> Result<ControllerBindings, Vec<String>> {
+    let mut bindings = ControllerBindings::default();
+    let mut errors = Vec::new();
+    for (name, value) in table {
+        if name == "deadzone" {
+            match value.as_integer().and_then(|deadzone| i16::try_from(deadzone).ok()) {
+                Some(deadzone) if deadzone >= 0 => bindings.deadzone = Some(deadzone),
+                _ => errors.push(format!("deadzone must be a number from 0 to 32767, got {}", value)),
+            }
+            continue;
+        }
+        let Some(button) = Button::from_string(name) else {
+            errors.push(format!("unknown button '{}'", name));
+            continue;
+        };
+        match value.as_str().and_then(|key| usize::from_str_radix(key, 16).ok().filter(|_| key.len() == 1)) {
+            Some(hex_key) => bindings.buttons.push((button, hex_key)),
+            None => errors.push(format!("{}: {} is not a hex key, use \"0\"-\"9\" or \"A\"-\"F\"", name, value)),
+        }
+    }
+    if errors.is_empty() { Ok(bindings) } else { Err(errors) }
+}
+
+// the background and foreground, optionally followed by the second plane and the blend of both
+fn parse_palette(colors: &[String]) -> Result<Palette, String> {
+    let colors = colors
+        .iter()
+        .map(|color| options::parse_color("a palette colour", color))
+        .collect::<Result<Vec<_>, _>>()?;
+    match colors.as_slice() {
+        [background, foreground] => Ok(Palette { colors: [*background, *foreground, *foreground, *foreground] }),
+        [background, foreground, plane2, both] => Ok(Palette { colors: [*background, *foreground, *plane2, *both] }),
+        _ => Err(format!("expected 2 or 4 colours, got {}", colors.len())),
+    }
+}
+
+// gives new users a file to edit
+fn write_default(path: &Path) {
+    if let Some(dir) = path.parent() {
+        if fs::create_dir_all(dir).and_then(|_| fs::write(path, DEFAULT_CONFIG)).is_ok() {
+            println!("Wrote default configuration to {}", path.display());
+        }
+    }
+}
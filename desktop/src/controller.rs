@@ -0,0 +1,163 @@
+/*
+    Game controller input.
+    Controllers can be plugged in and out while the emulator runs, and are assigned to players
+    in the order they were connected. The left stick works like the D-pad once it is pushed
+    past the deadzone. By default the D-pad and the A and B buttons use the key hints of the
+    rom database, falling back to 5/7/8/9 for movement and 6/4 for actions. The second
+    player uses the rom's player 2 hints where there are any.
+*/
+use std::collections::HashMap;
+
+use feo_core::Emulation;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+
+pub const DEFAULT_DEADZONE: i16 = 8000;
+
+const DEFAULT_BUTTONS: [(Button, &str, usize); 6] = [
+    (Button::DPadUp, "up", 0x5),
+    (Button::DPadDown, "down", 0x8),
+    (Button::DPadLeft, "left", 0x7),
+    (Button::DPadRight, "right", 0x9),
+    (Button::A, "a", 0x6),
+    (Button::B, "b", 0x4),
+];
+
+// hex keys of the controller buttons
+#[derive(Clone)]
+pub struct ControllerMap {
+    pub buttons: HashMap<Button, usize>,
+    pub deadzone: i16,
+}
+
+impl ControllerMap {
+    pub fn for_rom(emu: &Emulation, player: usize) -> Self {
+        let hints = emu.rom_info().map(|info| info.keys.as_slice()).unwrap_or_default();
+        let hint = |name: &str| {
+            let name = if player == 0 {
+                name.to_string()
+            } else {
+                // player2Up, player2A...
+                format!("player{}{}{}", player + 1, name[..1].to_uppercase(), &name[1..])
+            };
+            hints.iter().find(|(hint, _)| *hint == name).map(|(_, key)| *key as usize)
+        };
+        let buttons = DEFAULT_BUTTONS
+            .iter()
+            .map(|(button, name, default)| (*button, hint(name).unwrap_or(*default)))
+            .collect();
+        ControllerMap { buttons, deadzone: DEFAULT_DEADZONE }
+    }
+
+    // the stick presses the keys of the D-pad directions
+    fn stick_key(&self, axis: Axis, value: i16) -> Option<usize> {
+        let button = match axis {
+            Axis::LeftX if value < -self.deadzone => Button::DPadLeft,
+            Axis::LeftX if value > self.deadzone => Button::DPadRight,
+            Axis::LeftY if value < -self.deadzone => Button::DPadUp,
+            Axis::LeftY if value > self.deadzone => Button::DPadDown,
+            _ => return None,
+        };
+        self.buttons.get(&button).copied()
+    }
+}
+
+struct Pad {
+    controller: GameController,
+    buttons: Vec<usize>,
+    stick_x: Option<usize>,
+    stick_y: Option<usize>,
+}
+
+pub struct Controllers {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Pad>,
+    maps: Vec<ControllerMap>,
+    pressed: [bool; 16],
+}
+
+impl Controllers {
+    // maps are per player, the last one is used by any further players
+    pub fn new(subsystem: GameControllerSubsystem, maps: Vec<ControllerMap>) -> Self {
+        Controllers { subsystem, pads: Vec::new(), maps, pressed: [false; 16] }
+    }
+
+    // used when another rom is loaded
+    pub fn set_maps(&mut self, maps: Vec<ControllerMap>) {
+        self.maps = maps;
+    }
+
+    fn map(&self, player: usize) -> &ControllerMap {
+        &self.maps[player.min(self.maps.len() - 1)]
+    }
+
+    // handles controller events, returns false for other events
+    pub fn handle_event(&mut self, event: &Event, emu: &mut Emulation) -> bool {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => match self.subsystem.open(which) {
+                Ok(controller) => {
+                    println!("Controller connected: {} (player {})", controller.name(), self.pads.len() + 1);
+                    self.pads.push(Pad { controller, buttons: Vec::new(), stick_x: None, stick_y: None });
+                },
+                Err(err) => eprintln!("Unable to open controller: {}", err),
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(player) = self.player(which) {
+                    let pad = self.pads.remove(player);
+                    println!("Controller disconnected: {}", pad.controller.name());
+                }
+            },
+            Event::ControllerButtonDown { which, button, .. } => {
+                if let Some(player) = self.player(which) {
+                    if let Some(&key) = self.map(player).buttons.get(&button) {
+                        self.pads[player].buttons.push(key);
+                    }
+                }
+            },
+            Event::ControllerButtonUp { which, button, .. } => {
+                if let Some(player) = self.player(which) {
+                    if let Some(&key) = self.map(player).buttons.get(&button) {
+                        let buttons = &mut self.pads[player].buttons;
+                        if let Some(index) = buttons.iter().position(|held| *held == key) {
+                            buttons.remove(index);
+                        }
+                    }
+                }
+            },
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                if let Some(player) = self.player(which) {
+                    let key = self.map(player).stick_key(axis, value);
+                    match axis {
+                        Axis::LeftX => self.pads[player].stick_x = key,
+                        Axis::LeftY => self.pads[player].stick_y = key,
+                        _ => (),
+                    }
+                }
+            },
+            _ => return false,
+        }
+        self.update_keys(emu);
+        true
+    }
+
+    fn player(&self, instance_id: u32) -> Option<usize> {
+        self.pads.iter().position(|pad| pad.controller.instance_id() == instance_id)
+    }
+
+    // presses the keys held on any controller and releases the others
+    fn update_keys(&mut self, emu: &mut Emulation) {
+        let mut pressed = [false; 16];
+        for pad in &self.pads {
+            for key in pad.buttons.iter().chain(&pad.stick_x).chain(&pad.stick_y) {
+                pressed[*key] = true;
+            }
+        }
+        for (key, (now, before)) in pressed.iter().zip(self.pressed).enumerate() {
+            if *now != before {
+                emu.key_press(key, *now);
+            }
+        }
+        self.pressed = pressed;
+    }
+}
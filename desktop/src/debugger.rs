@@ -0,0 +1,180 @@
+/*
+    Debugger panel shown to the right of the game with F1.
+    It shows the registers, timers, stack and keypad, the disassembly around PC
+    and the memory around I. While paused, Up/Down/PageUp/PageDown move the cursor
+    in the disassembly, F9 sets or clears a breakpoint at the cursor and F10 runs one instruction.
+*/
+use feo_core::{disasm, Emulation};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::text;
+
+// width of the panel in characters
+const COLUMNS: u32 = 33;
+const DISASSEMBLY_LINES: usize = 9;
+const MEMORY_ROWS: usize = 4;
+const BYTES_PER_ROW: usize = 8;
+
+const BACKGROUND: Color = Color::RGB(24, 24, 28);
+const TEXT: Color = Color::RGB(220, 220, 220);
+const DIM: Color = Color::RGB(110, 110, 120);
+
+pub struct Debugger {
+    pub open: bool,
+    // address selected in the disassembly, follows PC unless moved while paused
+    cursor: Option<u16>,
+}
+
+// draws text on a grid of characters
+struct Grid<'a> {
+    canvas: &'a mut Canvas<Window>,
+    left: i32,
+    top: i32,
+    size: u32,
+}
+
+impl Grid<'_> {
+    fn text(&mut self, column: u32, row: u32, text: &str, color: Color) {
+        let x = self.left + (column * text::CHAR_WIDTH * self.size) as i32;
+        let y = self.top + (row * text::CHAR_HEIGHT * self.size) as i32;
+        text::draw(self.canvas, text, x, y, self.size, color);
+    }
+
+    // text on a bar of the highlight colour
+    fn highlight(&mut self, column: u32, row: u32, text: &str, highlight: Color) {
+        let x = self.left + (column * text::CHAR_WIDTH * self.size) as i32 - self.size as i32;
+        let y = self.top + (row * text::CHAR_HEIGHT * self.size) as i32 - self.size as i32;
+        let width = text::width(text, self.size) + self.size;
+        self.canvas.set_draw_color(highlight);
+        self.canvas.fill_rect(Rect::new(x, y, width, text::CHAR_HEIGHT * self.size)).unwrap();
+        self.text(column, row, text, BACKGROUND);
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger { open: false, cursor: None }
+    }
+
+    // font pixel size for a window of the given height
+    fn font_size(height: u32) -> u32 {
+        (height / 160).max(1)
+    }
+
+    // width of the panel in screen pixels
+    pub fn width(height: u32) -> u32 {
+        (COLUMNS + 2) * text::CHAR_WIDTH * Debugger::font_size(height)
+    }
+
+    // moves the disassembly cursor back to PC
+    pub fn follow_pc(&mut self) {
+        self.cursor = None;
+    }
+
+    // moves the cursor, returns false for keys the debugger doesn't use
+    pub fn handle_key(&mut self, key: Keycode, emu: &Emulation) -> bool {
+        let cursor = self.cursor.unwrap_or(emu.program_counter());
+        let offset: i32 = match key {
+            Keycode::Up => -2,
+            Keycode::Down => 2,
+            Keycode::PageUp => -2 * DISASSEMBLY_LINES as i32,
+            Keycode::PageDown => 2 * DISASSEMBLY_LINES as i32,
+            _ => return false,
+        };
+        let last = emu.memory_size() as i32 - 2;
+        self.cursor = Some((cursor as i32 + offset).clamp(0, last) as u16);
+        true
+    }
+
+    // sets or clears a breakpoint at the cursor
+    pub fn toggle_breakpoint(&mut self, emu: &mut Emulation) {
+        let address = self.cursor.unwrap_or(emu.program_counter());
+        if emu.toggle_breakpoint(address) {
+            println!("Breakpoint set at 0x{:03X}", address);
+        } else {
+            println!("Breakpoint cleared at 0x{:03X}", address);
+        }
+    }
+
+    pub fn draw(&self, emu: &Emulation, canvas: &mut Canvas<Window>, area: Rect, highlight: Color) {
+        canvas.set_draw_color(BACKGROUND);
+        canvas.fill_rect(area).unwrap();
+        let size = Debugger::font_size(area.height());
+        let margin = (text::CHAR_WIDTH * size) as i32;
+        let mut grid = Grid { canvas, left: area.x() + margin, top: area.y() + margin, size };
+
+        let pc = emu.program_counter();
+        let i = emu.index_register();
+        grid.text(0, 0, &format!("PC {:04X}  I {:04X}", pc, i), TEXT);
+        grid.text(0, 1, &format!("SP {:X}  DT {:02X}  ST {:02X}", emu.stack_pointer(), emu.delay_timer(), emu.sound_timer()), TEXT);
+
+        // registers with the keypad next to them, pressed keys highlighted
+        const KEYPAD: [[usize; 4]; 4] = [[0x1, 0x2, 0x3, 0xC], [0x4, 0x5, 0x6, 0xD], [0x7, 0x8, 0x9, 0xE], [0xA, 0x0, 0xB, 0xF]];
+        for (row, keys) in KEYPAD.iter().enumerate() {
+            let registers: Vec<String> = (row * 4..row * 4 + 4)
+                .map(|v| format!("V{:X} {:02X}", v, emu.registers()[v]))
+                .collect();
+            grid.text(0, 3 + row as u32, &registers.join(" "), TEXT);
+            for (column, key) in keys.iter().enumerate() {
+                let label = format!("{:X}", key);
+                let column = 25 + column as u32 * 2;
+                if emu.keys()[*key] {
+                    grid.highlight(column, 3 + row as u32, &label, highlight);
+                } else {
+                    grid.text(column, 3 + row as u32, &label, DIM);
+                }
+            }
+        }
+
+        // the innermost eight return addresses
+        let stack = emu.stack();
+        let shown = &stack[stack.len().saturating_sub(8)..];
+        grid.text(0, 8, "STACK", DIM);
+        for (row, chunk) in shown.chunks(4).enumerate() {
+            let addresses: Vec<String> = chunk.iter().map(|address| format!("{:04X}", address)).collect();
+            grid.text(6, 8 + row as u32, &addresses.join(" "), TEXT);
+        }
+
+        let cursor = self.cursor.unwrap_or(pc);
+        let lines = disasm::around(emu, cursor, DISASSEMBLY_LINES / 2, DISASSEMBLY_LINES);
+        for (row, line) in lines.iter().enumerate() {
+            let marker = if line.address == pc { '>' } else { ' ' };
+            let breakpoint = if emu.breakpoints().contains(&line.address) { '*' } else { ' ' };
+            let text = format!("{}{}{:04X} {:04X} {}", marker, breakpoint, line.address, line.opcode, line.text);
+            let row = 11 + row as u32;
+            if line.address == cursor && self.cursor.is_some() {
+                grid.highlight(0, row, &text, highlight);
+            } else {
+                grid.text(0, row, &text, if line.address == pc { TEXT } else { DIM });
+            }
+        }
+
+        // memory rows starting a row before the one holding I
+        let memory = emu.memory();
+        let start = (i as usize & !(BYTES_PER_ROW - 1)).saturating_sub(BYTES_PER_ROW);
+        for row in 0..MEMORY_ROWS {
+            let address = start + row * BYTES_PER_ROW;
+            if address >= memory.len() {
+                break;
+            }
+            let y = 21 + row as u32;
+            grid.text(0, y, &format!("{:04X}", address), DIM);
+            for column in 0..BYTES_PER_ROW {
+                let Some(byte) = memory.get(address + column) else {
+                    break;
+                };
+                let text = format!("{:02X}", byte);
+                let x = 5 + column as u32 * 3;
+                if address + column == i as usize {
+                    grid.highlight(x, y, &text, highlight);
+                } else {
+                    grid.text(x, y, &text, TEXT);
+                }
+            }
+        }
+    }
+}
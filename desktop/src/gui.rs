@@ -1,102 +1,633 @@
-use feo_core;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use feo_core::filter::{Filter, FilterMode, DEFAULT_FILTER_FRAMES};
+use feo_core::palette::{self, Palette};
+use feo_core::debug::Stop;
 use feo_core::Emulation;
 
 use sdl2::event::Event;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
-use sdl2::video::Window;
-use sdl2::keyboard::Keycode;
+use sdl2::video::{FullscreenType, Window};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::EventPump;
+
+use crate::browser::Browser;
+use crate::capture::{self, Recording};
+use crate::cheats;
+use crate::config::Config;
+use crate::controller::{ControllerMap, Controllers};
+use crate::debugger::Debugger;
+use crate::keymap::Keymap;
+use crate::options::Options;
+use crate::pacing::Pacing;
+use crate::rom;
+use crate::screen::Screen;
+use crate::settings::Settings;
+use crate::text;
+use crate::watcher::Watcher;
 
-const TICKS_PER_FRAME: usize = 10;
+// the timers count down at 60Hz, the instructions of a frame run before each count
+const FRAME_TIME: f64 = 1.0 / 60.0;
+// after a stall at most this much time is caught up on, at normal speed
+const MAX_CATCH_UP: f64 = 0.1;
+const SLOW_MOTION_SPEED: f64 = 0.25;
+const NOTICE_TIME: Duration = Duration::from_secs(2);
 
-const SCALE: u32 = 15;
-const WIDTH: u32 = (feo_core::SCREEN_WIDTH as u32) * SCALE;
-const HEIGHT: u32 = (feo_core::SCREEN_HEIGHT as u32) * SCALE;
+struct Gui<'a> {
+    emu: &'a mut Emulation,
+    options: &'a Options,
+    config: &'a Config,
+    canvas: Canvas<Window>,
+    screen: Screen<'a>,
+    controllers: Controllers,
+    // path of the running rom, none until one is chosen in the browser
+    rom: Option<PathBuf>,
+    // watches the running rom's file with --watch
+    watcher: Option<Watcher>,
+    browser: Option<Browser>,
+    debugger: Debugger,
+    keymap: Keymap,
+    settings: Settings,
+    // built-in themes followed by the ones from the config file
+    palettes: Vec<(String, Palette)>,
+    palette: usize,
+    // the selected palette with the changes from the rom database and command line
+    colors: Palette,
+    filter: Filter,
+    recording: Option<Recording>,
+    // frames used by the decay and or filters
+    filter_frames: u8,
+    paused: bool,
+    fast_forward: bool,
+    fast_forward_held: bool,
+    slow_motion: bool,
+    // instructions per second, and the fraction of an instruction carried to the next frame
+    ips: f64,
+    instructions: f64,
+    // emulated time still to be run, in seconds
+    accumulator: f64,
+    last_update: Instant,
+    pacing: Pacing,
+    show_pacing: bool,
+    // a message shown at the bottom of the display for a moment
+    notice: Option<(String, Instant)>,
+}
 
-pub fn init(emu: &mut Emulation) {
+pub fn init(emu: &mut Emulation, options: &Options, config: &Config) {
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem
-        .window("FeO8 - Chip-8 Emulator", WIDTH, HEIGHT)
-        .position_centered()
-        .opengl()
-        .build()
-        .unwrap();
+    let width = feo_core::SCREEN_WIDTH as u32 * options.scale;
+    let height = feo_core::SCREEN_HEIGHT as u32 * options.scale;
+    let mut window = video_subsystem.window("FeO8 - Chip-8 Emulator", width, height);
+    window.position_centered().resizable().opengl();
+    if options.fullscreen {
+        window.fullscreen_desktop();
+    }
+    let window = window.build().unwrap();
     let mut canvas = window.into_canvas().present_vsync().build().unwrap();
     canvas.clear();
     canvas.present();
+    let texture_creator = canvas.texture_creator();
 
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let controller_subsystem = sdl_context.game_controller().unwrap();
+    // the defaults until a rom is loaded, so pads already work in the rom browser
+    let maps = vec![ControllerMap::for_rom(emu, 0), ControllerMap::for_rom(emu, 1)];
+    let controllers = Controllers::new(controller_subsystem, maps);
+    let settings = Settings::load();
+    let palettes: Vec<(String, Palette)> = palette::THEMES
+        .iter()
+        .map(|(name, palette)| (name.to_string(), *palette))
+        .chain(config.palettes.iter().cloned())
+        .collect();
+    let palette = match options.palette.as_ref().or(settings.palette.as_ref()) {
+        Some(name) => palettes.iter().position(|(palette, _)| palette.eq_ignore_ascii_case(name)).unwrap_or_else(|| {
+            eprintln!("Unknown palette '{}'", name);
+            0
+        }),
+        None => 0,
+    };
+    let filter = options.filter.or_else(|| settings.filter.as_deref().and_then(FilterMode::parse)).unwrap_or_default();
+    let filter_frames = match filter {
+        FilterMode::Decay(frames) | FilterMode::Or(frames) => frames,
+        _ => DEFAULT_FILTER_FRAMES,
+    };
+    let mut gui = Gui {
+        emu,
+        options,
+        config,
+        canvas,
+        screen: Screen::new(&texture_creator, options.integer_scaling),
+        controllers,
+        rom: options.rom.clone(),
+        watcher: None,
+        browser: None,
+        debugger: Debugger::new(),
+        keymap: Keymap::default(),
+        settings,
+        palettes,
+        palette,
+        colors: Palette::default(),
+        filter: Filter::new(filter),
+        recording: None,
+        filter_frames,
+        paused: options.paused,
+        fast_forward: false,
+        fast_forward_held: false,
+        slow_motion: false,
+        ips: 0.0,
+        instructions: 0.0,
+        accumulator: 0.0,
+        last_update: Instant::now(),
+        pacing: Pacing::new(),
+        show_pacing: options.stats,
+        notice: None,
+    };
+    gui.update_colors();
+    match &gui.rom {
+        Some(_) => {
+            gui.restart_display();
+            gui.rom_loaded();
+        },
+        None => gui.browser = Some(Browser::new(&config.rom_dir)),
+    }
+    gui.run(&mut event_pump);
+}
 
-    'game_loop: loop {
-        for evt in event_pump.poll_iter() {
-            match evt {
-                Event::Quit{..} | Event::KeyDown{keycode: Some(Keycode::Escape), ..}=> {
-                    break 'game_loop;
-                },
-
-                Event::KeyDown{keycode: Some(key), ..} => {
-                    if let Some(k) = key_input(key) {
-                        emu.key_press(k, true);
+impl Gui<'_> {
+    fn run(&mut self, event_pump: &mut EventPump) {
+        loop {
+            for evt in event_pump.poll_iter() {
+                if !self.handle_event(evt) {
+                    if self.recording.is_some() {
+                        self.toggle_recording();
                     }
+                    return;
+                }
+            }
+            if self.browser.is_none() && self.watcher.as_mut().is_some_and(Watcher::poll) {
+                self.reload();
+            }
+            let now = Instant::now();
+            let elapsed = (now - self.last_update).as_secs_f64();
+            self.last_update = now;
+            match &mut self.browser {
+                Some(browser) => {
+                    browser.draw(&mut self.canvas, rgb(self.colors.colors[0]), rgb(self.colors.colors[1]));
                 },
-                Event::KeyUp{keycode: Some(key), ..} => {
-                    if let Some(k) = key_input(key) {
-                        emu.key_press(k, false);
-                    }
+                None => {
+                    self.run_frames(elapsed);
+                    self.draw();
                 },
+            }
+            let present = Instant::now();
+            self.canvas.present();
+            self.pacing.presented();
+            // without vsync present returns at once, so wait for the next frame instead of spinning
+            if present.elapsed() < Duration::from_millis(1) {
+                thread::sleep(Duration::from_secs_f64(FRAME_TIME).saturating_sub(now.elapsed()));
+            }
+        }
+    }
+
+    // returns false when the emulator should quit
+    fn handle_event(&mut self, evt: Event) -> bool {
+        if self.controllers.handle_event(&evt, self.emu) {
+            return true;
+        }
+        match evt {
+            Event::Quit{..} => return false,
+
+            // roms can be dropped on the window at any time
+            Event::DropFile{filename, ..} => {
+                self.open(Path::new(&filename));
+            },
+
+            Event::KeyDown{keycode: Some(key), ..} if self.browser.is_some() => {
+                return self.browser_key(key);
+            },
+
+            Event::KeyDown{keycode: Some(Keycode::Escape), ..} => return false,
+
+            // F11 and Alt+Enter switch between the window and borderless fullscreen
+            Event::KeyDown{keycode: Some(Keycode::F11), ..} => {
+                self.toggle_fullscreen();
+            },
+            Event::KeyDown{keycode: Some(Keycode::Return), keymod, ..} if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                self.toggle_fullscreen();
+            },
+
+            // Ctrl+O shows the rom browser, in the folder of the running rom
+            Event::KeyDown{keycode: Some(Keycode::O), keymod, ..} if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                let dir = self.rom.as_deref().and_then(Path::parent).unwrap_or(&self.config.rom_dir);
+                self.browser = Some(Browser::new(dir));
+            },
+
+            // F2 restarts the program, Shift+F2 also clears all of ram and reloads the rom
+            Event::KeyDown{keycode: Some(Keycode::F2), keymod, ..} => {
+                if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                    let buffer = self.emu.rom().to_vec();
+                    self.emu.reset();
+                    self.emu.load(&buffer);
+                    self.options.apply(self.emu);
+                } else {
+                    self.emu.soft_reset();
+                }
+                self.restart_display();
+            },
 
-                _ => ()
+            // F5 reloads the rom file, to pick up changes made to it
+            Event::KeyDown{keycode: Some(Keycode::F5), ..} => {
+                self.reload();
+            },
+
+            // F3 switches to the next palette, Shift+F3 to the previous one
+            Event::KeyDown{keycode: Some(Keycode::F3), keymod, ..} => {
+                let count = self.palettes.len();
+                self.palette = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                    (self.palette + count - 1) % count
+                } else {
+                    (self.palette + 1) % count
+                };
+                let name = self.palettes[self.palette].0.clone();
+                println!("Palette: {}", name);
+                self.settings.palette = Some(name);
+                self.settings.save();
+                self.update_colors();
+            },
+
+            // F4 switches between the flicker filters
+            Event::KeyDown{keycode: Some(Keycode::F4), ..} => {
+                let mode = self.filter.mode().next(self.filter_frames);
+                println!("Filter: {}", mode);
+                self.filter.set_mode(mode);
+                self.settings.filter = Some(mode.to_string());
+                self.settings.save();
+            },
+
+            // F12 saves a screenshot, Shift+F12 starts and stops recording a GIF
+            Event::KeyDown{keycode: Some(Keycode::F12), keymod, ..} => {
+                if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                    self.toggle_recording();
+                } else {
+                    self.screenshot();
+                }
+            },
+
+            // F7 pauses and resumes the emulation, F8 runs a single frame while paused
+            Event::KeyDown{keycode: Some(Keycode::F7), ..} => {
+                self.paused = !self.paused;
+                self.debugger.follow_pc();
+            },
+            Event::KeyDown{keycode: Some(Keycode::F8), ..} if self.paused => {
+                if let Some(stop) = self.run_frame() {
+                    self.stopped(stop);
+                }
+                self.debugger.follow_pc();
+            },
+
+            // F1 shows the debugger, Shift+F1 the frame pacing, F9 sets a breakpoint in it and F10 steps a single instruction
+            Event::KeyDown{keycode: Some(Keycode::F1), keymod, ..} => {
+                if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                    self.show_pacing = !self.show_pacing;
+                } else {
+                    self.toggle_debugger();
+                }
+            },
+            Event::KeyDown{keycode: Some(Keycode::F9), ..} if self.debugger.open => {
+                self.debugger.toggle_breakpoint(self.emu);
+            },
+            Event::KeyDown{keycode: Some(Keycode::F10), ..} if self.paused => {
+                self.emu.step();
+                self.filter.push(self.emu);
+                self.debugger.follow_pc();
+            },
+            Event::KeyDown{keycode: Some(key), ..}
+                if self.paused && self.debugger.open && self.debugger.handle_key(key, self.emu) => {},
+
+            // holding Tab fast-forwards, Shift+Tab toggles it, Backquote toggles slow motion
+            Event::KeyDown{keycode: Some(Keycode::Tab), keymod, repeat: false, ..} => {
+                if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                    self.fast_forward = !self.fast_forward;
+                } else {
+                    self.fast_forward_held = true;
+                }
+            },
+            Event::KeyUp{keycode: Some(Keycode::Tab), ..} => {
+                self.fast_forward_held = false;
+            },
+            Event::KeyDown{keycode: Some(Keycode::Backquote), repeat: false, ..} => {
+                self.slow_motion = !self.slow_motion;
+            },
+
+            // F6 toggles all cheats, Ctrl+1-9 toggles a single one
+            Event::KeyDown{keycode: Some(Keycode::F6), ..} => {
+                cheats::toggle_all(self.emu);
+            },
+            Event::KeyDown{keycode: Some(key), keymod, ..}
+                if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) && cheat_index(key).is_some() => {
+                cheats::toggle(self.emu, cheat_index(key).unwrap());
+            },
+
+            Event::KeyDown{keycode: Some(key), ..} => {
+                if let Some(k) = self.keymap.hex_key(key) {
+                    self.emu.key_press(k, true);
+                }
+            },
+            Event::KeyUp{keycode: Some(key), ..} => {
+                if let Some(k) = self.keymap.hex_key(key) {
+                    self.emu.key_press(k, false);
+                }
+            },
+
+            _ => ()
+        }
+        true
+    }
+
+    // Escape goes back to the running rom, or quits when there is none
+    fn browser_key(&mut self, key: Keycode) -> bool {
+        let Some(browser) = &mut self.browser else {
+            return true;
+        };
+        if key == Keycode::Escape {
+            if self.rom.is_none() {
+                return false;
             }
+            self.browser = None;
+            return true;
         }
-        for _ in 0..TICKS_PER_FRAME {
-            emu.tick();
+        let (_, page) = Browser::layout(&self.canvas);
+        if let Some(path) = browser.handle_key(key, page) {
+            self.open(&path);
         }
-        emu.tick_timers();
-        draw_screen(emu, &mut canvas);
+        true
     }
-}
 
-fn draw_screen(emu: &Emulation, canvas: &mut Canvas<Window>) {
-    // Clear canvas as black
-    canvas.set_draw_color(Color::RGB(185, 55, 94));
-    canvas.clear();
-    let screen_buf = emu.get_display();
-    // Now set draw color to white, iterate through each point and see if it should be drawn
-    canvas.set_draw_color(Color::RGB(255, 122, 162));
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
-            // Convert our 1D array's index into a 2D (x,y) position
-            let x = (i % feo_core::SCREEN_WIDTH) as u32;
-            let y = (i / feo_core::SCREEN_WIDTH) as u32;
-    // Draw a rectangle at (x,y), scaled up by our SCALE value
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
-            canvas.fill_rect(rect).unwrap();
+    // loads a rom file in place of the running one, returns false if it can't be read
+    fn open(&mut self, path: &Path) -> bool {
+        let buffer = match rom::read(path) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                eprintln!("{}", err);
+                if let Some(browser) = &mut self.browser {
+                    browser.set_error(err);
+                }
+                return false;
+            },
+        };
+        rom::load(self.emu, &buffer, self.options);
+        self.rom = Some(path.to_path_buf());
+        self.browser = None;
+        self.restart_display();
+        self.rom_loaded();
+        true
+    }
+
+    // loads the rom file again, with --keep-settings the platform, quirks and speed stay as they were
+    // instead of being looked up again, as the rom database won't know a rom that is being worked on
+    fn reload(&mut self) {
+        let Some(path) = self.rom.clone() else {
+            return;
+        };
+        let (platform, quirks, ticks, ips) = (self.emu.platform(), self.emu.quirks(), self.emu.ticks_per_frame(), self.ips);
+        if !self.open(&path) {
+            self.notify(String::from("RELOAD FAILED"));
+            return;
+        }
+        if self.options.keep_settings {
+            self.emu.set_platform(platform);
+            self.emu.set_quirks(quirks);
+            self.emu.set_ticks_per_frame(ticks);
+            self.ips = ips;
+        }
+        println!("Reloaded {}", path.display());
+        self.notify(format!("RELOADED {}", path.file_name().unwrap_or_default().to_string_lossy()));
+    }
+
+    fn notify(&mut self, text: String) {
+        self.notice = Some((text, Instant::now()));
+    }
+
+    // picks up the settings that depend on the rom
+    fn rom_loaded(&mut self) {
+        let Some(path) = &self.rom else {
+            return;
+        };
+        // both a rom from the command line and one picked later are watched
+        if self.options.watch && self.watcher.as_ref().is_none_or(|watcher| watcher.path() != path) {
+            self.watcher = Some(Watcher::new(path));
+        }
+        let title = match self.emu.rom_info() {
+            Some(info) => format!("FeO8 - {}", info.title),
+            None => format!("FeO8 - {}", path.file_name().unwrap_or_default().to_string_lossy()),
+        };
+        self.canvas.window_mut().set_title(&title).unwrap();
+        self.keymap = self.config.keymap(path, self.emu.rom_hash());
+        self.controllers.set_maps(self.config.controller_maps(path, self.emu));
+        self.ips = self.options.ips.unwrap_or(self.emu.ticks_per_frame() * 60) as f64;
+        self.instructions = 0.0;
+        self.accumulator = 0.0;
+        self.update_colors();
+    }
+
+    // the default palette takes the colours of the rom database,
+    // and colours from the command line replace those of any palette
+    fn update_colors(&mut self) {
+        let mut palette = match self.palette {
+            0 => Palette::for_rom(self.emu.rom_info()),
+            index => self.palettes[index].1,
+        };
+        if let Some(bg) = self.options.bg {
+            palette.colors[0] = bg;
+        }
+        if let Some(fg) = self.options.fg {
+            palette.colors[1] = fg;
+        }
+        self.colors = palette;
+    }
+
+    // the filters shouldn't show frames from before a reset
+    fn restart_display(&mut self) {
+        self.filter.clear();
+        self.filter.push(self.emu);
+    }
+
+    // runs the frames due in the real time that has passed, scaled by the speed.
+    // This keeps the emulation at 60 frames per second whatever the refresh rate of the display
+    fn run_frames(&mut self, elapsed: f64) {
+        if self.paused {
+            self.accumulator = 0.0;
+            return;
+        }
+        let speed = if self.fast_forward || self.fast_forward_held {
+            self.options.fast_forward as f64
+        } else if self.slow_motion {
+            SLOW_MOTION_SPEED
+        } else {
+            1.0
+        };
+        self.accumulator = (self.accumulator + elapsed * speed).min(MAX_CATCH_UP * speed.max(1.0));
+        while self.accumulator >= FRAME_TIME {
+            self.accumulator -= FRAME_TIME;
+            if let Some(stop) = self.run_frame() {
+                self.stopped(stop);
+                break;
+            }
+        }
+    }
+
+    // runs the instructions of one frame, returns what stopped it before the end
+    fn run_frame(&mut self) -> Option<Stop> {
+        self.instructions += self.ips * FRAME_TIME;
+        let ticks = self.instructions as u32;
+        self.instructions -= ticks as f64;
+        if let Some(stop) = self.emu.run(ticks) {
+            return Some(stop);
+        }
+        self.emu.tick_timers();
+        self.pacing.emulated_frame(ticks);
+        self.filter.push(self.emu);
+        if let Some(recording) = &mut self.recording {
+            if let Err(err) = recording.add_frame(&self.filter, &self.colors) {
+                eprintln!("{}", err);
+                self.recording = None;
+            }
+        }
+        None
+    }
+
+    // the part of the window left of the debugger
+    fn game_area(&self) -> Rect {
+        let (width, height) = self.canvas.output_size().unwrap();
+        // a minimised window can be 0 pixels wide
+        let panel = if self.debugger.open { Debugger::width(height).min(width.saturating_sub(1)) } else { 0 };
+        Rect::new(0, 0, width.saturating_sub(panel), height)
+    }
+
+    // size of a pixel of the 64x32 display in the window
+    fn scale(&self) -> usize {
+        let viewport = self.screen.viewport(self.game_area(), feo_core::SCREEN_WIDTH, feo_core::SCREEN_HEIGHT);
+        (viewport.width() as usize / feo_core::SCREEN_WIDTH).max(1)
+    }
+
+    // saved at the size the display is shown in the window
+    fn screenshot(&self) {
+        let viewport = self.screen.viewport(self.game_area(), self.filter.width(), self.filter.height());
+        let scale = viewport.width() as usize / self.filter.width().max(1);
+        match capture::screenshot(&self.config.captures_dir, &self.filter, &self.colors, scale.max(1)) {
+            Ok(path) => println!("Saved screenshot {}", path.display()),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(recording) => match recording.finish() {
+                Ok(path) => println!("Saved recording {}", path.display()),
+                Err(err) => eprintln!("{}", err),
+            },
+            None => match Recording::start(&self.config.captures_dir, self.scale()) {
+                Ok(recording) => {
+                    println!("Recording {}", recording.path().display());
+                    self.recording = Some(recording);
+                },
+                Err(err) => eprintln!("{}", err),
+            },
+        }
+    }
+
+    // pauses with the debugger open
+    fn stopped(&mut self, stop: Stop) {
+        match stop {
+            Stop::Fault(fault) => {
+                println!("Stopped by {} at 0x{:03X}", fault, self.emu.program_counter());
+                self.notify(fault.to_string().to_uppercase());
+            },
+            _ => println!("Breakpoint hit at 0x{:03X}", self.emu.program_counter()),
+        }
+        self.paused = true;
+        self.debugger.follow_pc();
+        if !self.debugger.open {
+            self.toggle_debugger();
+        }
+    }
+
+    // the window grows to fit the panel next to the game, in fullscreen the game makes room for it
+    fn toggle_debugger(&mut self) {
+        self.debugger.open = !self.debugger.open;
+        let window = self.canvas.window_mut();
+        if window.fullscreen_state() == FullscreenType::Off {
+            let (width, height) = window.size();
+            let panel = Debugger::width(height);
+            let width = if self.debugger.open { width + panel } else { width.saturating_sub(panel) };
+            window.set_size(width, height).unwrap();
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        if let Err(err) = window.set_fullscreen(fullscreen) {
+            eprintln!("Unable to switch fullscreen: {}", err);
+        }
+    }
+
+    fn draw(&mut self) {
+        let (width, height) = self.canvas.output_size().unwrap();
+        let game = self.game_area();
+        self.screen.draw(&mut self.canvas, &self.filter, &self.colors, game);
+        if self.debugger.open {
+            let area = Rect::new(game.width() as i32, 0, width.saturating_sub(game.width()), height);
+            self.debugger.draw(self.emu, &mut self.canvas, area, rgb(self.colors.colors[1]));
+        }
+        if self.show_pacing {
+            // on a dark bar so it can be read over any palette
+            let size = (height / 240).max(1);
+            let summary = self.pacing.summary();
+            let bar = Rect::new(0, 0, text::width(summary, size) + 2 * size, text::CHAR_HEIGHT * size + size);
+            self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+            self.canvas.fill_rect(bar).unwrap();
+            text::draw(&mut self.canvas, summary, size as i32, size as i32, size, Color::RGB(255, 255, 255));
+        }
+        if let Some((notice, shown)) = &self.notice {
+            if shown.elapsed() > NOTICE_TIME {
+                self.notice = None;
+                return;
+            }
+            let size = (height / 160).max(1);
+            let notice = notice.to_uppercase();
+            let bar_height = text::CHAR_HEIGHT * size + size;
+            let bar = Rect::new(0, game.height() as i32 - bar_height as i32, text::width(&notice, size) + 2 * size, bar_height);
+            self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+            self.canvas.fill_rect(bar).unwrap();
+            text::draw(&mut self.canvas, &notice, size as i32, bar.y() + size as i32, size, Color::RGB(255, 255, 255));
         }
     }
-    canvas.present();
 }
 
-fn key_input(key: Keycode) -> Option<usize> {
+fn rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::RGB(r, g, b)
+}
+
+fn cheat_index(key: Keycode) -> Option<usize> {
     match key {
-        Keycode::Num1 => Some(0x1),
-        Keycode::Num2 => Some(0x2),
-        Keycode::Num3 => Some(0x3),
-        Keycode::Num4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
+        Keycode::Num1 => Some(0),
+        Keycode::Num2 => Some(1),
+        Keycode::Num3 => Some(2),
+        Keycode::Num4 => Some(3),
+        Keycode::Num5 => Some(4),
+        Keycode::Num6 => Some(5),
+        Keycode::Num7 => Some(6),
+        Keycode::Num8 => Some(7),
+        Keycode::Num9 => Some(8),
         _ => None,
     }
 }
-
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use sdl2::keyboard::Keycode;
+
+// the default layout uses the left side of a qwerty keyboard
+//   1 2 3 C      1 2 3 4
+//   4 5 6 D  ->  Q W E R
+//   7 8 9 E      A S D F
+//   A 0 B F      Z X C V
+const DEFAULT_KEYS: [(usize, Keycode); 16] = [
+    (0x1, Keycode::Num1),
+    (0x2, Keycode::Num2),
+    (0x3, Keycode::Num3),
+    (0xC, Keycode::Num4),
+    (0x4, Keycode::Q),
+    (0x5, Keycode::W),
+    (0x6, Keycode::E),
+    (0xD, Keycode::R),
+    (0x7, Keycode::A),
+    (0x8, Keycode::S),
+    (0x9, Keycode::D),
+    (0xE, Keycode::F),
+    (0xA, Keycode::Z),
+    (0x0, Keycode::X),
+    (0xB, Keycode::C),
+    (0xF, Keycode::V),
+];
+
+// maps keyboard keys to the 16 keys of the hex keypad, several keys can map to the same hex key
+#[derive(Clone, Default)]
+pub struct Keymap {
+    keys: HashMap<Keycode, usize>,
+}
+
+impl Keymap {
+    pub fn qwerty() -> Self {
+        let mut keymap = Keymap::default();
+        for (hex_key, key) in DEFAULT_KEYS {
+            keymap.bind(key, hex_key);
+        }
+        keymap
+    }
+
+    pub fn bind(&mut self, key: Keycode, hex_key: usize) {
+        self.keys.insert(key, hex_key);
+    }
+
+    // removes all keys mapped to a hex key
+    pub fn unbind(&mut self, hex_key: usize) {
+        self.keys.retain(|_, bound| *bound != hex_key);
+    }
+
+    pub fn hex_key(&self, key: Keycode) -> Option<usize> {
+        self.keys.get(&key).copied()
+    }
+}
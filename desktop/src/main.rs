@@ -1,22 +1,53 @@
 use std::env;
+use std::process;
+
 use feo_core::Emulation;
-use std::fs::File;
-use std::io::Read;
 
+use config::Config;
+use options::{Command, Options};
 
+mod browser;
+mod capture;
+mod cheats;
+mod config;
+mod controller;
+mod debugger;
 mod gui;
+mod keymap;
+mod options;
+mod pacing;
+mod rom;
+mod screen;
+mod settings;
+mod text;
+mod watcher;
 
 fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            println!("{}", options::USAGE);
+            return;
+        },
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, options::USAGE);
+            process::exit(2);
+        },
+    };
 
-    let args: Vec<_> = env::args().collect();
-    if args.len() != 2 {
-        println!("Path to game rom unspecified");
-        return;
-    }
-    let mut rom = File::open(&args[1]).expect("Unable to open file");
-    let mut buffer = Vec::new();
-    rom.read_to_end(&mut buffer).unwrap();
+    // without a rom the gui starts in the rom browser
     let mut emu = Emulation::new();
-    emu.load(&buffer);
-    gui::init(&mut emu);
-}
\ No newline at end of file
+    if let Some(path) = &options.rom {
+        match rom::read(path) {
+            Ok(buffer) => rom::load(&mut emu, &buffer, &options),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            },
+        }
+    }
+    let config = Config::load();
+    gui::init(&mut emu, &options, &config);
+    cheats::save(&emu);
+}
+
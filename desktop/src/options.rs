@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+
+use feo_core::filter::FilterMode;
+use feo_core::{Emulation, Platform, Quirks};
+
+pub const USAGE: &str = "\
+Usage: desktop [OPTIONS] [ROM]
+
+Without a rom a rom browser is shown.
+
+Options:
+  --scale <N>          size of a CHIP-8 pixel in screen pixels (default 15)
+  --ips <N>            instructions per second (default: from the rom database or platform),
+                       independent of the display's refresh rate
+  --palette <NAME>     colour palette: feo8, classic, green, amber, contrast, octo
+                       or one from the config file
+  --fg <RRGGBB>        foreground colour
+  --bg <RRGGBB>        background colour
+  --platform <ID>      originalChip8, modernChip8, chip48, superchip1, superchip or xochip
+  --quirks <LIST>      comma separated quirks to enable, prefix with - to disable,
+                       e.g. shift,-vblank (shift, memoryIncrementByX, memoryLeaveIUnchanged,
+                       wrap, jump, vblank, logic)
+  --filter <MODE>      flicker filter: off, blend, decay[:FRAMES] or or[:FRAMES]
+  --seed <N>           seed for the random number generator
+  --fast-forward <N>   speed multiplier while fast-forwarding (default 4)
+  --stats              show frame pacing statistics
+  --paused             start paused
+  --fullscreen         start in fullscreen
+  --smooth-scaling     fill the window instead of scaling by whole numbers
+  --watch              reload the rom when its file changes
+  --keep-settings      keep the platform, quirks and speed when the rom is reloaded
+  -h, --help           show this message";
+
+pub struct Options {
+    pub rom: Option<PathBuf>,
+    pub scale: u32,
+    pub ips: Option<u32>,
+    pub palette: Option<String>,
+    pub fg: Option<[u8; 3]>,
+    pub bg: Option<[u8; 3]>,
+    pub platform: Option<Platform>,
+    pub quirks: Vec<(String, bool)>,
+    pub filter: Option<FilterMode>,
+    pub seed: Option<u64>,
+    pub fast_forward: u32,
+    pub stats: bool,
+    pub paused: bool,
+    pub fullscreen: bool,
+    pub integer_scaling: bool,
+    pub watch: bool,
+    pub keep_settings: bool,
+}
+
+// what the command line asked for
+pub enum Command {
+    Run(Options),
+    Help,
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+        let mut options = Options {
+            rom: None,
+            scale: 15,
+            ips: None,
+            palette: None,
+            fg: None,
+            bg: None,
+            platform: None,
+            quirks: Vec::new(),
+            filter: None,
+            seed: None,
+            fast_forward: 4,
+            stats: false,
+            paused: false,
+            fullscreen: false,
+            integer_scaling: true,
+            watch: false,
+            keep_settings: false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "--scale" => options.scale = parse_number(&arg, &value(&arg)?, 1)?,
+                "--ips" => options.ips = Some(parse_number(&arg, &value(&arg)?, 1)?),
+                "--palette" => options.palette = Some(value(&arg)?),
+                "--fg" => options.fg = Some(parse_color(&arg, &value(&arg)?)?),
+                "--bg" => options.bg = Some(parse_color(&arg, &value(&arg)?)?),
+                "--platform" => {
+                    let id = value(&arg)?;
+                    let platform = Platform::from_id(&id).ok_or(format!("unknown platform '{}'", id))?;
+                    options.platform = Some(platform);
+                },
+                "--quirks" => options.quirks.extend(parse_quirks(&value(&arg)?)?),
+                "--filter" => {
+                    let mode = value(&arg)?;
+                    options.filter = Some(FilterMode::parse(&mode).ok_or(format!("unknown filter '{}'", mode))?);
+                },
+                "--seed" => {
+                    let seed = value(&arg)?;
+                    options.seed = Some(seed.parse().map_err(|_| format!("invalid seed '{}'", seed))?);
+                },
+                "--fast-forward" => options.fast_forward = parse_number(&arg, &value(&arg)?, 1)?,
+                "--stats" => options.stats = true,
+                "--paused" => options.paused = true,
+                "--fullscreen" => options.fullscreen = true,
+                "--smooth-scaling" => options.integer_scaling = false,
+                "--watch" => options.watch = true,
+                "--keep-settings" => options.keep_settings = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+                _ if options.rom.is_some() => return Err(format!("unexpected argument '{}'", arg)),
+                _ => options.rom = Some(PathBuf::from(arg)),
+            }
+        }
+
+        Ok(Command::Run(options))
+    }
+
+    // overrides what the rom database selected, needs to be done after every load
+    pub fn apply(&self, emu: &mut Emulation) {
+        if let Some(platform) = self.platform {
+            emu.set_platform(platform);
+        }
+        let mut quirks = emu.quirks();
+        for (name, enabled) in &self.quirks {
+            quirks.set(name, *enabled);
+        }
+        emu.set_quirks(quirks);
+        if let Some(ips) = self.ips {
+            emu.set_ticks_per_frame((ips / 60).max(1));
+        }
+        if let Some(seed) = self.seed {
+            emu.set_seed(seed);
+        }
+    }
+}
+
+fn parse_number(option: &str, value: &str, min: u32) -> Result<u32, String> {
+    match value.parse() {
+        Ok(number) if number >= min => Ok(number),
+        _ => Err(format!("{} must be a whole number of at least {}, got '{}'", option, min, value)),
+    }
+}
+
+// colours are written as RRGGBB, optionally starting with #
+pub fn parse_color(option: &str, value: &str) -> Result<[u8; 3], String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    match u32::from_str_radix(hex, 16) {
+        Ok(color) if hex.len() == 6 => Ok([(color >> 16) as u8, (color >> 8) as u8, color as u8]),
+        _ => Err(format!("{} must be a colour like ff7aa2, got '{}'", option, value)),
+    }
+}
+
+fn parse_quirks(value: &str) -> Result<Vec<(String, bool)>, String> {
+    value
+        .split(',')
+        .map(|quirk| {
+            let (name, enabled) = match quirk.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (quirk, true),
+            };
+            if Quirks::default().get(name).is_none() {
+                return Err(format!("unknown quirk '{}'", name));
+            }
+            Ok((name.to_string(), enabled))
+        })
+        .collect()
+}
@@ -0,0 +1,73 @@
+/*
+    Frame pacing statistics, shown with Shift+F1.
+    Counts the frames shown and emulated and the instructions run, and measures how
+    evenly the frames are shown. The numbers are updated once a second.
+*/
+use std::time::{Duration, Instant};
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Pacing {
+    start: Instant,
+    last_present: Option<Instant>,
+    // times between presented frames in the current interval, in seconds
+    frame_times: Vec<f64>,
+    emulated_frames: u32,
+    instructions: u64,
+    summary: String,
+}
+
+impl Pacing {
+    pub fn new() -> Self {
+        Pacing {
+            start: Instant::now(),
+            last_present: None,
+            frame_times: Vec::new(),
+            emulated_frames: 0,
+            instructions: 0,
+            summary: String::from("FPS -"),
+        }
+    }
+
+    pub fn emulated_frame(&mut self, instructions: u32) {
+        self.emulated_frames += 1;
+        self.instructions += instructions as u64;
+    }
+
+    // to be called after every present, updates the summary once an interval has passed
+    pub fn presented(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_present {
+            self.frame_times.push((now - last).as_secs_f64());
+        }
+        self.last_present = Some(now);
+
+        let elapsed = (now - self.start).as_secs_f64();
+        if elapsed < INTERVAL.as_secs_f64() || self.frame_times.is_empty() {
+            return;
+        }
+        let count = self.frame_times.len() as f64;
+        let mean = self.frame_times.iter().sum::<f64>() / count;
+        let variance = self.frame_times.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / count;
+        let min = self.frame_times.iter().copied().fold(f64::MAX, f64::min);
+        let max = self.frame_times.iter().copied().fold(0.0, f64::max);
+        self.summary = format!(
+            "FPS {:.1}  EMU {:.1}  IPS {:.0}  FRAME {:.1}MS ({:.1}-{:.1})  JITTER {:.2}MS",
+            count / elapsed,
+            self.emulated_frames as f64 / elapsed,
+            self.instructions as f64 / elapsed,
+            mean * 1000.0,
+            min * 1000.0,
+            max * 1000.0,
+            variance.sqrt() * 1000.0,
+        );
+        self.start = now;
+        self.frame_times.clear();
+        self.emulated_frames = 0;
+        self.instructions = 0;
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+}
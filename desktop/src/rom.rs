@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::Path;
+
+use feo_core::Emulation;
+
+use crate::cheats;
+use crate::options::Options;
+
+// reads a rom file, checking that it fits in memory
+pub fn read(path: &Path) -> Result<Vec<u8>, String> {
+    let buffer = fs::read(path).map_err(|err| format!("Unable to open {}: {}", path.display(), err))?;
+    if buffer.len() > feo_core::MAX_ROM_SIZE {
+        return Err(format!("{} is too large to be a rom ({} bytes)", path.display(), buffer.len()));
+    }
+    Ok(buffer)
+}
+
+// replaces the running program, keeping the cheats of each rom in their own file
+pub fn load(emu: &mut Emulation, buffer: &[u8], options: &Options) {
+    if !emu.rom().is_empty() {
+        cheats::save(emu);
+        emu.cheats_mut().clear();
+    }
+    emu.reset();
+    emu.load(buffer);
+    options.apply(emu);
+    print_info(emu);
+    cheats::load(emu);
+}
+
+// shows what the rom database knows about the game
+fn print_info(emu: &Emulation) {
+    match emu.rom_info() {
+        Some(info) => {
+            println!("{} ({}, {} instructions per frame)", info.title, info.platform.id(), info.tickrate);
+            for (action, key) in &info.keys {
+                println!("  {:<12} {:X}", action, key);
+            }
+        },
+        None => println!("Unknown rom {}, running as {}", emu.rom_hash(), emu.platform().id()),
+    }
+}
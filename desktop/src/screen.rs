@@ -0,0 +1,67 @@
+/*
+    Draws the display into the window through a streaming texture.
+    The display keeps its 2:1 shape whatever the size of the window, with black bars
+    filling the rest. Integer scaling makes every CHIP-8 pixel the same whole number of
+    screen pixels, fractional scaling fills as much of the window as fits.
+*/
+use feo_core::filter::Filter;
+use feo_core::palette::Palette;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+pub struct Screen<'a> {
+    creator: &'a TextureCreator<WindowContext>,
+    // the texture is replaced when the display switches resolution
+    texture: Option<(Texture<'a>, usize, usize)>,
+    pixels: Vec<u8>,
+    integer_scaling: bool,
+}
+
+impl<'a> Screen<'a> {
+    pub fn new(creator: &'a TextureCreator<WindowContext>, integer_scaling: bool) -> Self {
+        Screen { creator, texture: None, pixels: Vec::new(), integer_scaling }
+    }
+
+    // where a display of width x height pixels is drawn in the area
+    pub fn viewport(&self, area: Rect, width: usize, height: usize) -> Rect {
+        let (width, height) = (width.max(1) as u32, height.max(1) as u32);
+        let (view_width, view_height) = match (area.width() / width).min(area.height() / height) {
+            scale if self.integer_scaling && scale > 0 => (width * scale, height * scale),
+            // fractional, or a window too small for even one pixel per pixel
+            _ => {
+                let view_width = area.width().min(area.height() * width / height);
+                (view_width, view_width * height / width)
+            },
+        };
+        let left = area.x() + (area.width() - view_width) as i32 / 2;
+        let top = area.y() + (area.height() - view_height) as i32 / 2;
+        Rect::new(left, top, view_width.max(1), view_height.max(1))
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, filter: &Filter, palette: &Palette, area: Rect) {
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.fill_rect(area).unwrap();
+        let (width, height) = (filter.width(), filter.height());
+        if width == 0 {
+            return;
+        }
+        if !matches!(&self.texture, Some((_, w, h)) if *w == width && *h == height) {
+            let texture = self
+                .creator
+                .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+                .unwrap();
+            self.texture = Some((texture, width, height));
+        }
+        let viewport = self.viewport(area, width, height);
+        let Some((texture, _, _)) = &mut self.texture else {
+            return;
+        };
+
+        self.pixels.clear();
+        self.pixels.extend(filter.pixels(palette).flatten());
+        texture.update(None, &self.pixels, width * 3).unwrap();
+        canvas.copy(texture, None, viewport).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+/*
+    Choices made while the emulator runs that are kept for the next start,
+    stored in settings.toml next to the config file. Unlike config.toml it is
+    written by the emulator, so it is kept separate from the user's file.
+*/
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub palette: Option<String>,
+    pub filter: Option<String>,
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        Config::path().and_then(|path| path.parent().map(|dir| dir.join(SETTINGS_FILE)))
+    }
+
+    // missing or unreadable settings fall back to the defaults
+    pub fn load() -> Settings {
+        Settings::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Settings::path() else {
+            return;
+        };
+        let text = toml::to_string(self).unwrap();
+        let result = match path.parent() {
+            Some(dir) => fs::create_dir_all(dir).and_then(|_| fs::write(&path, text)),
+            None => fs::write(&path, text),
+        };
+        if let Err(err) = result {
+            eprintln!("Unable to save settings to {}: {}", path.display(), err);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+/*
+    Text drawn with the 4x5 pixel CHIP-8 font.
+    The digits and A-F are the font of the interpreter, the other letters and symbols
+    are drawn in the same style. Lowercase letters are shown as uppercase.
+*/
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+// space taken by a character in font pixels, including the gap after it
+pub const CHAR_WIDTH: u32 = 5;
+pub const CHAR_HEIGHT: u32 = 6;
+
+const GLYPHS: [(char, [u8; 5]); 43] = [
+    ('G', [0xF0, 0x80, 0xB0, 0x90, 0xF0]),
+    ('H', [0x90, 0x90, 0xF0, 0x90, 0x90]),
+    ('I', [0x70, 0x20, 0x20, 0x20, 0x70]),
+    ('J', [0x10, 0x10, 0x10, 0x90, 0xF0]),
+    ('K', [0x90, 0xA0, 0xC0, 0xA0, 0x90]),
+    ('L', [0x80, 0x80, 0x80, 0x80, 0xF0]),
+    ('M', [0x90, 0xF0, 0xF0, 0x90, 0x90]),
+    ('N', [0x90, 0xD0, 0xB0, 0x90, 0x90]),
+    ('O', [0x60, 0x90, 0x90, 0x90, 0x60]),
+    ('P', [0xF0, 0x90, 0xF0, 0x80, 0x80]),
+    ('Q', [0x60, 0x90, 0x90, 0xB0, 0x70]),
+    ('R', [0xF0, 0x90, 0xF0, 0xA0, 0x90]),
+    ('S', [0x70, 0x80, 0x60, 0x10, 0xE0]),
+    ('T', [0xE0, 0x40, 0x40, 0x40, 0x40]),
+    ('U', [0x90, 0x90, 0x90, 0x90, 0xF0]),
+    ('V', [0x90, 0x90, 0x90, 0x90, 0x60]),
+    ('W', [0x90, 0x90, 0xF0, 0xF0, 0x90]),
+    ('X', [0x90, 0x90, 0x60, 0x90, 0x90]),
+    ('Y', [0xA0, 0xA0, 0x40, 0x40, 0x40]),
+    ('Z', [0xF0, 0x10, 0x60, 0x80, 0xF0]),
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x40]),
+    (',', [0x00, 0x00, 0x00, 0x40, 0x80]),
+    (':', [0x00, 0x40, 0x00, 0x40, 0x00]),
+    ('-', [0x00, 0x00, 0xF0, 0x00, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0xF0]),
+    ('+', [0x00, 0x40, 0xE0, 0x40, 0x00]),
+    ('=', [0x00, 0xF0, 0x00, 0xF0, 0x00]),
+    ('/', [0x10, 0x10, 0x20, 0x40, 0x80]),
+    ('>', [0x80, 0x40, 0x20, 0x40, 0x80]),
+    ('<', [0x20, 0x40, 0x80, 0x40, 0x20]),
+    ('(', [0x20, 0x40, 0x40, 0x40, 0x20]),
+    (')', [0x40, 0x20, 0x20, 0x20, 0x40]),
+    ('[', [0x60, 0x40, 0x40, 0x40, 0x60]),
+    (']', [0x60, 0x20, 0x20, 0x20, 0x60]),
+    ('!', [0x40, 0x40, 0x40, 0x00, 0x40]),
+    ('?', [0xE0, 0x10, 0x60, 0x00, 0x40]),
+    ('#', [0xA0, 0xF0, 0xA0, 0xF0, 0xA0]),
+    ('%', [0x90, 0x10, 0x20, 0x40, 0x90]),
+    ('*', [0x00, 0xA0, 0x40, 0xA0, 0x00]),
+    ('&', [0x40, 0xA0, 0x40, 0xA0, 0xD0]),
+    ('\'', [0x40, 0x40, 0x00, 0x00, 0x00]),
+    ('"', [0xA0, 0xA0, 0x00, 0x00, 0x00]),
+];
+
+fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase();
+    if let Some(digit) = c.to_digit(16) {
+        let start = digit as usize * 5;
+        let mut glyph = [0; 5];
+        glyph.copy_from_slice(&feo_core::FONT_SET[start..start + 5]);
+        return glyph;
+    }
+    GLYPHS.iter().find(|(glyph_char, _)| *glyph_char == c).map(|(_, glyph)| *glyph).unwrap_or_else(|| glyph('?'))
+}
+
+// width of a line of text in screen pixels
+pub fn width(text: &str, size: u32) -> u32 {
+    text.chars().count() as u32 * CHAR_WIDTH * size
+}
+
+// draws a line of text with its top left corner at x, y, each font pixel size x size screen pixels
+pub fn draw(canvas: &mut Canvas<Window>, text: &str, x: i32, y: i32, size: u32, color: Color) {
+    canvas.set_draw_color(color);
+    for (i, c) in text.chars().enumerate() {
+        let left = x + (i as u32 * CHAR_WIDTH * size) as i32;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..4 {
+                if bits & (0x80 >> column) != 0 {
+                    let rect = Rect::new(left + (column * size) as i32, y + (row as u32 * size) as i32, size, size);
+                    canvas.fill_rect(rect).unwrap();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+/*
+    Polls the rom file for changes, so programs can be reloaded when an assembler writes them.
+    Polling needs no file system services and works the same everywhere. A change is only
+    reported once the file's size and modification time have stayed the same for a poll,
+    so a file still being written isn't loaded half done.
+*/
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+type Stamp = Option<(SystemTime, u64)>;
+
+pub struct Watcher {
+    path: PathBuf,
+    loaded: Stamp,
+    // a change that hasn't settled yet
+    changed: Option<Stamp>,
+    last_poll: Instant,
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl Watcher {
+    pub fn new(path: &Path) -> Self {
+        Watcher { path: path.to_path_buf(), loaded: stamp(path), changed: None, last_poll: Instant::now() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // returns true once when the file has changed and settled
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        let current = stamp(&self.path);
+        // a missing file is usually being replaced, wait for it to come back
+        if current.is_none() || current == self.loaded {
+            self.changed = None;
+            return false;
+        }
+        if self.changed == Some(current) {
+            self.loaded = current;
+            self.changed = None;
+            return true;
+        }
+        self.changed = Some(current);
+        false
+    }
+}
@@ -0,0 +1,132 @@
+/*
+    Packets of the GDB remote serial protocol over a TCP stream.
+    A packet is sent as $data#checksum and acknowledged with + (or - to have it sent again),
+    until the debugger switches acknowledgements off with QStartNoAckMode.
+    A single 0x03 byte outside of a packet asks the running program to stop.
+*/
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+const INTERRUPT: u8 = 0x03;
+
+pub enum Packet {
+    Command(String),
+    Interrupt,
+}
+
+pub struct Connection {
+    stream: TcpStream,
+    // bytes received but not yet part of a whole packet
+    buffer: Vec<u8>,
+    no_ack: bool,
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Self {
+        Connection { stream, buffer: Vec::new(), no_ack: false }
+    }
+
+    pub fn disable_acks(&mut self) {
+        self.no_ack = true;
+    }
+
+    pub fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum(data.as_bytes()));
+        self.stream.write_all(packet.as_bytes())
+    }
+
+    // waits for the next packet, none when the debugger has disconnected
+    pub fn receive(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            if let Some(packet) = self.parse()? {
+                return Ok(Some(packet));
+            }
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    // returns true if an interrupt arrived, without waiting; other packets are kept for later
+    pub fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let result = self.fill();
+        self.stream.set_nonblocking(false)?;
+        match result {
+            Ok(false) => return Err(ErrorKind::ConnectionAborted.into()),
+            Err(err) if err.kind() != ErrorKind::WouldBlock => return Err(err),
+            _ => (),
+        }
+        match self.buffer.iter().position(|byte| *byte == INTERRUPT) {
+            Some(index) => {
+                self.buffer.remove(index);
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    // reads what has arrived, returns false at the end of the stream
+    fn fill(&mut self) -> io::Result<bool> {
+        let mut bytes = [0; 4096];
+        let count = self.stream.read(&mut bytes)?;
+        self.buffer.extend_from_slice(&bytes[..count]);
+        Ok(count > 0)
+    }
+
+    // takes the first whole packet from the buffer
+    fn parse(&mut self) -> io::Result<Option<Packet>> {
+        // acknowledgements from the debugger need no answer
+        let start = self.buffer.iter().position(|byte| !matches!(byte, b'+' | b'-')).unwrap_or(self.buffer.len());
+        self.buffer.drain(..start);
+        match self.buffer.first() {
+            None => return Ok(None),
+            Some(&INTERRUPT) => {
+                self.buffer.remove(0);
+                return Ok(Some(Packet::Interrupt));
+            },
+            Some(b'$') => (),
+            // noise between packets
+            Some(_) => {
+                self.buffer.remove(0);
+                return self.parse();
+            },
+        }
+        let Some(end) = self.buffer.iter().position(|byte| *byte == b'#') else {
+            return Ok(None);
+        };
+        if self.buffer.len() < end + 3 {
+            return Ok(None);
+        }
+        let packet: Vec<u8> = self.buffer.drain(..end + 3).collect();
+        let data = &packet[1..end];
+        let valid = std::str::from_utf8(&packet[end + 1..])
+            .ok()
+            .and_then(|sum| u8::from_str_radix(sum, 16).ok())
+            .is_some_and(|sum| sum == checksum(data));
+        if !self.no_ack {
+            self.stream.write_all(if valid { b"+" } else { b"-" })?;
+        }
+        if !valid {
+            return self.parse();
+        }
+        Ok(Some(Packet::Command(String::from_utf8_lossy(&unescape(data)).into_owned())))
+    }
+}
+
+// binary data escapes $, #, } and * as } followed by the byte xor 0x20
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut bytes = data.iter();
+    let mut unescaped = Vec::with_capacity(data.len());
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'}' => unescaped.extend(bytes.next().map(|byte| byte ^ 0x20)),
+            _ => unescaped.push(*byte),
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,298 @@
+/*
+    GDB remote serial protocol server for CHIP-8 programs.
+    Runs a rom without a display and waits for a debugger on a local TCP port, e.g.
+        gdb --port 1234 game.ch8
+    and in GDB: target remote localhost:1234
+    The program starts stopped before its first instruction. While it runs, frames go at
+    60 per second like in the other frontends, until a breakpoint, a watchpoint, an interrupt
+    or the program's end.
+*/
+use std::env;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, TcpListener};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use feo_core::{Emulation, Platform};
+
+use connection::{Connection, Packet};
+use stub::Action;
+
+mod connection;
+mod stub;
+
+const USAGE: &str = "\
+Usage: gdb [OPTIONS] ROM
+
+Options:
+  --port <N>         TCP port to listen on at 127.0.0.1 (default 1234)
+  --platform <ID>    originalChip8, modernChip8, chip48, superchip1, superchip or xochip
+  --ips <N>          instructions per second (default: from the rom database or platform)
+  -h, --help         show this message";
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+struct Options {
+    rom: String,
+    port: u16,
+    platform: Option<Platform>,
+    ips: Option<u32>,
+}
+
+fn parse_options() -> Result<Option<Options>, String> {
+    let mut rom = None;
+    let mut port = 1234;
+    let mut platform = None;
+    let mut ips = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--port" => {
+                let value = value(&arg)?;
+                port = value.parse().map_err(|_| format!("invalid port '{}'", value))?;
+            },
+            "--platform" => {
+                let id = value(&arg)?;
+                platform = Some(Platform::from_id(&id).ok_or(format!("unknown platform '{}'", id))?);
+            },
+            "--ips" => {
+                let value = value(&arg)?;
+                ips = Some(value.parse().ok().filter(|ips| *ips > 0).ok_or(format!("invalid instructions per second '{}'", value))?);
+            },
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if rom.is_some() => return Err(format!("unexpected argument '{}'", arg)),
+            _ => rom = Some(arg),
+        }
+    }
+    let rom = rom.ok_or("no rom given")?;
+    Ok(Some(Options { rom, port, platform, ips }))
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        },
+    };
+    let buffer = match fs::read(&options.rom) {
+        Ok(buffer) if buffer.len() <= feo_core::MAX_ROM_SIZE => buffer,
+        Ok(_) => {
+            eprintln!("{} is too large to be a rom", options.rom);
+            process::exit(1);
+        },
+        Err(err) => {
+            eprintln!("Unable to open {}: {}", options.rom, err);
+            process::exit(1);
+        },
+    };
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, options.port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Unable to listen on port {}: {}", options.port, err);
+            process::exit(1);
+        },
+    };
+
+    let mut emu = Emulation::new();
+    load(&mut emu, &buffer, &options);
+    // one debugger at a time; after it detaches the next one finds the program where it was left
+    loop {
+        println!("Waiting for GDB on {}", listener.local_addr().unwrap());
+        let stream = match listener.accept() {
+            Ok((stream, address)) => {
+                println!("Debugger connected from {}", address);
+                stream
+            },
+            Err(err) => {
+                eprintln!("Unable to accept a connection: {}", err);
+                continue;
+            },
+        };
+        let _ = stream.set_nodelay(true);
+        match serve(&mut emu, Connection::new(stream), &options) {
+            Ok(true) => {
+                println!("Killed, restarting the program");
+                load(&mut emu, &buffer, &options);
+            },
+            Ok(false) => println!("Debugger detached"),
+            Err(err) => eprintln!("Connection lost: {}", err),
+        }
+    }
+}
+
+fn load(emu: &mut Emulation, buffer: &[u8], options: &Options) {
+    emu.reset();
+    emu.load(buffer);
+    if let Some(platform) = options.platform {
+        emu.set_platform(platform);
+    }
+    if let Some(ips) = options.ips {
+        emu.set_ticks_per_frame((ips / 60).max(1));
+    }
+}
+
+// answers the debugger until it goes away, returns true if it killed the program
+fn serve(emu: &mut Emulation, mut connection: Connection, options: &Options) -> io::Result<bool> {
+    while let Some(packet) = connection.receive()? {
+        let Packet::Command(command) = packet else {
+            // already stopped
+            connection.send(&format!("S{:02x}", stub::SIGINT))?;
+            continue;
+        };
+        match stub::handle(emu, &command) {
+            Action::Reply(reply) => {
+                connection.send(&reply)?;
+                if command == "QStartNoAckMode" {
+                    connection.disable_acks();
+                }
+            },
+            Action::Step(reply) => connection.send(&reply)?,
+            Action::Continue => {
+                let reply = run(emu, &mut connection, options)?;
+                connection.send(&reply)?;
+            },
+            Action::Detach => {
+                connection.send("OK")?;
+                return Ok(false);
+            },
+            Action::Kill => return Ok(true),
+        }
+    }
+    Ok(false)
+}
+
+// runs frames in real time until something stops the program, returns the stop reply
+fn run(emu: &mut Emulation, connection: &mut Connection, options: &Options) -> io::Result<String> {
+    let ticks = options.ips.map_or(emu.ticks_per_frame(), |ips| (ips / 60).max(1));
+    let mut next_frame = Instant::now();
+    loop {
+        if let Some(stop) = emu.run(ticks) {
+            return Ok(stub::stop_reply(stop));
+        }
+        if emu.exited() {
+            return Ok(stub::exit_reply());
+        }
+        emu.tick_timers();
+        if connection.interrupted()? {
+            return Ok(format!("S{:02x}", stub::SIGINT));
+        }
+        next_frame += FRAME_TIME;
+        thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+
+    // the debugger end of a connection
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn send(&mut self, command: &str) -> String {
+            let checksum = command.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+            self.stream.write_all(format!("${}#{:02x}", command, checksum).as_bytes()).unwrap();
+            let mut packet = Vec::new();
+            let mut byte = [0];
+            while packet.len() < 3 || packet[packet.len() - 3] != b'#' {
+                self.stream.read_exact(&mut byte).unwrap();
+                // acknowledgements come before the reply
+                if !(packet.is_empty() && byte[0] == b'+') {
+                    packet.push(byte[0]);
+                }
+            }
+            String::from_utf8(packet[1..packet.len() - 3].to_vec()).unwrap()
+        }
+    }
+
+    #[test]
+    fn debugs_over_tcp() {
+        let rom = [
+            0x60, 0x05, // 200: V0 = 5
+            0xA3, 0x00, // 202: I = 300
+            0xF0, 0x55, // 204: store V0 at I
+            0xA3, 0x00, // 206: I = 300 again, the store moved it
+            0xF0, 0x65, // 208: load V0 from I
+            0xE0, 0x00, // 20a: not an instruction
+        ];
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let options = Options { rom: String::new(), port: 0, platform: None, ips: None };
+            let mut emu = Emulation::new();
+            load(&mut emu, &rom, &options);
+            let (stream, _) = listener.accept().unwrap();
+            serve(&mut emu, Connection::new(stream), &options).unwrap()
+        });
+        let mut client = Client { stream: TcpStream::connect(address).unwrap() };
+
+        assert_eq!(client.send("QStartNoAckMode"), "OK");
+        assert_eq!(client.send("g"), format!("{}0000020000{}", "00".repeat(16), "0000"));
+        assert_eq!(client.send("m200,4"), "6005a300");
+        assert_eq!(client.send("M300,1:aa"), "OK");
+        assert_eq!(client.send("m300,1"), "aa");
+
+        assert_eq!(client.send("Z0,202,2"), "OK");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("p11"), "0202");
+        assert_eq!(client.send("s"), "S05");
+        assert_eq!(client.send("p11"), "0204");
+        assert_eq!(client.send("z0,202,2"), "OK");
+
+        assert_eq!(client.send("Z2,300,1"), "OK");
+        assert_eq!(client.send("c"), "T05watch:300;");
+        assert_eq!(client.send("m300,1"), "05");
+        assert_eq!(client.send("z2,300,1"), "OK");
+        assert_eq!(client.send("Z3,300,1"), "OK");
+        assert_eq!(client.send("c"), "T05rwatch:300;");
+        assert_eq!(client.send("z3,300,1"), "OK");
+
+        // the program runs into data, and stays stopped there
+        assert_eq!(client.send("c"), "S04");
+        assert_eq!(client.send("p11"), "020a");
+        assert_eq!(client.send("?"), "S04");
+        assert_eq!(client.send("s"), "S04");
+        assert_eq!(client.send("c"), "S04");
+
+        client.stream.write_all(b"$k#6b").unwrap();
+        assert!(server.join().unwrap());
+    }
+
+    #[test]
+    fn reports_the_exit() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let options = Options { rom: String::new(), port: 0, platform: Platform::from_id("superchip"), ips: None };
+            let mut emu = Emulation::new();
+            // EXIT
+            load(&mut emu, &[0x00, 0xFD], &options);
+            let (stream, _) = listener.accept().unwrap();
+            serve(&mut emu, Connection::new(stream), &options).unwrap()
+        });
+        let mut client = Client { stream: TcpStream::connect(address).unwrap() };
+
+        assert_eq!(client.send("QStartNoAckMode"), "OK");
+        assert_eq!(client.send("c"), "W00");
+        assert_eq!(client.send("?"), "W00");
+        assert_eq!(client.send("c"), "W00");
+
+        client.stream.write_all(b"$k#6b").unwrap();
+        assert!(server.join().unwrap());
+    }
+}
@@ -0,0 +1,284 @@
+/*
+    Answers the commands of a GDB session for one emulated CHIP-8 machine.
+    The registers are V0-VF, I, PC, SP and the delay and sound timers, described to the debugger
+    by the target description XML. Values wider than a byte are sent big endian, the byte order
+    CHIP-8 uses in memory. Memory is the RAM of the current platform.
+    Software and hardware breakpoints are the same thing here, watchpoints come from feo_core.
+    A program that faults, e.g. on data that isn't an instruction, stops with SIGILL and stays
+    there until it is killed. One that ends itself with EXIT is reported as exited.
+*/
+use feo_core::debug::{Stop, WatchKind, Watchpoint};
+use feo_core::Emulation;
+
+// name, size in bits and type of each register, in the order of the g packet
+const REGISTERS: [(&str, u32, &str); 21] = [
+    ("v0", 8, "uint8"), ("v1", 8, "uint8"), ("v2", 8, "uint8"), ("v3", 8, "uint8"),
+    ("v4", 8, "uint8"), ("v5", 8, "uint8"), ("v6", 8, "uint8"), ("v7", 8, "uint8"),
+    ("v8", 8, "uint8"), ("v9", 8, "uint8"), ("va", 8, "uint8"), ("vb", 8, "uint8"),
+    ("vc", 8, "uint8"), ("vd", 8, "uint8"), ("ve", 8, "uint8"), ("vf", 8, "uint8"),
+    ("i", 16, "data_ptr"),
+    ("pc", 16, "code_ptr"),
+    ("sp", 8, "uint8"),
+    ("dt", 8, "uint8"),
+    ("st", 8, "uint8"),
+];
+const I: usize = 16;
+const PC: usize = 17;
+const SP: usize = 18;
+const DT: usize = 19;
+const ST: usize = 20;
+
+// signals reported to the debugger
+pub const SIGINT: u8 = 2;
+pub const SIGILL: u8 = 4;
+pub const SIGTRAP: u8 = 5;
+
+// what to do after a command
+pub enum Action {
+    Reply(String),
+    Continue,
+    // the reply to a step, or to a continue that stops straight away
+    Step(String),
+    Detach,
+    Kill,
+}
+
+pub fn target_xml() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n<target version=\"1.0\">\n  <feature name=\"org.feo8.chip8.core\">\n",
+    );
+    for (number, (name, bits, kind)) in REGISTERS.iter().enumerate() {
+        xml.push_str(&format!("    <reg name=\"{}\" bitsize=\"{}\" type=\"{}\" regnum=\"{}\"/>\n", name, bits, kind, number));
+    }
+    xml.push_str("  </feature>\n</target>\n");
+    xml
+}
+
+pub fn stop_reply(stop: Stop) -> String {
+    match stop {
+        Stop::Breakpoint(_) => format!("S{:02x}", SIGTRAP),
+        Stop::Watchpoint(watchpoint) => {
+            let kind = match watchpoint.kind {
+                WatchKind::Write => "watch",
+                WatchKind::Read => "rwatch",
+                WatchKind::Access => "awatch",
+            };
+            format!("T{:02x}{}:{:x};", SIGTRAP, kind, watchpoint.address)
+        },
+        Stop::Fault(_) => format!("S{:02x}", SIGILL),
+    }
+}
+
+// the reply for a program that ended itself
+pub fn exit_reply() -> String {
+    String::from("W00")
+}
+
+// why the program is stopped when it isn't running
+fn stopped_reply(emu: &Emulation) -> String {
+    match emu.fault() {
+        Some(fault) => stop_reply(Stop::Fault(fault)),
+        None if emu.exited() => exit_reply(),
+        None => format!("S{:02x}", SIGTRAP),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    (0..text.len()).step_by(2).map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok()).collect()
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 16).ok()
+}
+
+fn error(code: u8) -> String {
+    format!("E{:02x}", code)
+}
+
+fn ok() -> String {
+    String::from("OK")
+}
+
+fn read_register(emu: &Emulation, number: usize) -> Option<Vec<u8>> {
+    let value = match number {
+        0..=15 => return Some(vec![emu.registers()[number]]),
+        I => return Some(emu.index_register().to_be_bytes().to_vec()),
+        PC => return Some(emu.program_counter().to_be_bytes().to_vec()),
+        SP => emu.stack_pointer() as u8,
+        DT => emu.delay_timer(),
+        ST => emu.sound_timer(),
+        _ => return None,
+    };
+    Some(vec![value])
+}
+
+fn write_register(emu: &mut Emulation, number: usize, bytes: &[u8]) -> bool {
+    let size = REGISTERS.get(number).map_or(0, |(_, bits, _)| *bits as usize / 8);
+    if size == 0 || bytes.len() != size {
+        return false;
+    }
+    let word = || u16::from_be_bytes([bytes[0], bytes[1]]);
+    match number {
+        0..=15 => emu.set_register(number, bytes[0]),
+        I => emu.set_index_register(word()),
+        PC => emu.set_program_counter(word()),
+        SP => emu.set_stack_pointer(bytes[0] as u16),
+        DT => emu.set_delay_timer(bytes[0]),
+        _ => emu.set_sound_timer(bytes[0]),
+    }
+    true
+}
+
+// start and length of a memory range, as in "addr,length"
+fn parse_range(text: &str) -> Option<(u32, u32)> {
+    let (address, length) = text.split_once(',')?;
+    Some((parse_number(address)?, parse_number(length)?))
+}
+
+// handles one command packet
+pub fn handle(emu: &mut Emulation, command: &str) -> Action {
+    let reply = match command.split_at(command.len().min(1)) {
+        ("?", _) => stopped_reply(emu),
+        ("g", _) => hex(&(0..REGISTERS.len()).flat_map(|number| read_register(emu, number).unwrap_or_default()).collect::<Vec<u8>>()),
+        ("G", values) => write_registers(emu, values),
+        ("p", number) => match parse_number(number).and_then(|number| read_register(emu, number as usize)) {
+            Some(bytes) => hex(&bytes),
+            None => error(1),
+        },
+        ("P", assignment) => {
+            let written = assignment
+                .split_once('=')
+                .and_then(|(number, value)| Some((parse_number(number)?, parse_hex(value)?)))
+                .is_some_and(|(number, bytes)| write_register(emu, number as usize, &bytes));
+            if written { ok() } else { error(1) }
+        },
+        ("m", range) => read_memory(emu, range),
+        ("M", arguments) => write_memory(emu, arguments),
+        ("c", address) | ("s", address) => {
+            if !address.is_empty() {
+                match parse_number(address) {
+                    Some(address) => emu.set_program_counter(address as u16),
+                    None => return Action::Reply(error(1)),
+                }
+            }
+            // a faulted or exited program can't go on, it stops again straight away
+            if emu.fault().is_some() || emu.exited() {
+                return Action::Step(stopped_reply(emu));
+            }
+            if command.starts_with('c') {
+                return Action::Continue;
+            }
+            emu.step();
+            return Action::Step(stopped_reply(emu));
+        },
+        ("Z", arguments) => set_point(emu, arguments, true),
+        ("z", arguments) => set_point(emu, arguments, false),
+        ("D", _) => return Action::Detach,
+        ("k", _) => return Action::Kill,
+        // there is a single thread, which is always alive
+        ("H", _) | ("T", _) => ok(),
+        _ => query(command),
+    };
+    Action::Reply(reply)
+}
+
+fn write_registers(emu: &mut Emulation, values: &str) -> String {
+    let Some(bytes) = parse_hex(values) else {
+        return error(1);
+    };
+    let mut offset = 0;
+    for (number, (_, bits, _)) in REGISTERS.iter().enumerate() {
+        let size = *bits as usize / 8;
+        let Some(value) = bytes.get(offset..offset + size) else {
+            return error(1);
+        };
+        write_register(emu, number, value);
+        offset += size;
+    }
+    ok()
+}
+
+fn read_memory(emu: &Emulation, range: &str) -> String {
+    let memory = emu.memory();
+    match parse_range(range) {
+        // reads past the end are cut short
+        Some((address, length)) if (address as usize) < memory.len() => {
+            let end = (address as usize + length as usize).min(memory.len());
+            hex(&memory[address as usize..end])
+        },
+        _ => error(1),
+    }
+}
+
+fn write_memory(emu: &mut Emulation, arguments: &str) -> String {
+    let parsed = arguments.split_once(':').and_then(|(range, data)| Some((parse_range(range)?, parse_hex(data)?)));
+    match parsed {
+        Some(((address, length), bytes)) if bytes.len() == length as usize && address as usize + bytes.len() <= emu.memory_size() => {
+            emu.write_memory(address as u16, &bytes);
+            ok()
+        },
+        _ => error(1),
+    }
+}
+
+// Z0/Z1 breakpoints and Z2-Z4 write, read and access watchpoints, as "type,addr,kind"
+fn set_point(emu: &mut Emulation, arguments: &str, insert: bool) -> String {
+    let mut parts = arguments.split(',');
+    let (Some(kind), Some(address), Some(length)) = (parts.next(), parts.next().and_then(parse_number), parts.next().and_then(parse_number)) else {
+        return error(1);
+    };
+    let address = address as u16;
+    let watch = match kind {
+        "0" | "1" => {
+            emu.set_breakpoint(address, insert);
+            return ok();
+        },
+        "2" => WatchKind::Write,
+        "3" => WatchKind::Read,
+        "4" => WatchKind::Access,
+        _ => return String::new(),
+    };
+    let watchpoint = Watchpoint { address, length: length as u16, kind: watch };
+    if insert {
+        emu.add_watchpoint(watchpoint);
+    } else {
+        emu.remove_watchpoint(watchpoint);
+    }
+    ok()
+}
+
+// general queries, unknown ones get an empty reply
+fn query(command: &str) -> String {
+    if command.starts_with("qSupported") {
+        return String::from("PacketSize=1000;qXfer:features:read+;QStartNoAckMode+");
+    }
+    if let Some(arguments) = command.strip_prefix("qXfer:features:read:") {
+        let Some((annex, range)) = arguments.split_once(':') else {
+            return error(1);
+        };
+        if annex != "target.xml" {
+            return error(0);
+        }
+        let Some((offset, length)) = parse_range(range) else {
+            return error(1);
+        };
+        let xml = target_xml();
+        let start = (offset as usize).min(xml.len());
+        let end = (start + length as usize).min(xml.len());
+        let more = if end < xml.len() { 'm' } else { 'l' };
+        return format!("{}{}", more, &xml[start..end]);
+    }
+    match command {
+        "QStartNoAckMode" => ok(),
+        "qAttached" => String::from("1"),
+        "qC" => String::from("QC1"),
+        "qfThreadInfo" => String::from("m1"),
+        "qsThreadInfo" => String::from("l"),
+        "qSymbol::" => ok(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,460 @@
+/*
+    Libretro core, for playing CHIP-8 games in RetroArch and other libretro frontends.
+    The frontend loads this library and calls the retro_* functions below; the core draws
+    frames in XRGB8888, plays the buzzer as a square wave and saves states for rewinding
+    and netplay. Core options choose the platform, quirks, speed, palette and flicker filter.
+    RetroPad mapping, as in the desktop frontend: the D-pad and A/B use the rom database's key
+    hints, falling back to 5/7/8/9 and 6/4. The other buttons of player 1 press the remaining keys:
+    X 1, Y 2, L 3, R C, L2 D, R2 E, L3 A, R3 B, Select F, Start 0.
+    The SUPER-CHIP user flags are the save RAM, so frontends keep high scores between sessions.
+*/
+// the safety requirements of the exported functions are those of libretro.h
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+use std::sync::Mutex;
+
+use feo_core::audio::Audio;
+use feo_core::cheats::Cheat;
+use feo_core::filter::Filter;
+use feo_core::palette::Palette;
+use feo_core::{Emulation, Platform, Quirks};
+
+use ffi::*;
+use options::Options;
+
+mod ffi;
+mod options;
+
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: u32 = 48000;
+const PLAYERS: u32 = 2;
+
+const PAD_BUTTONS: [(u32, &str, usize); 6] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, "up", 0x5),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, "down", 0x8),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, "left", 0x7),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, "right", 0x9),
+    (RETRO_DEVICE_ID_JOYPAD_A, "a", 0x6),
+    (RETRO_DEVICE_ID_JOYPAD_B, "b", 0x4),
+];
+const EXTRA_BUTTONS: [(u32, usize); 10] = [
+    (RETRO_DEVICE_ID_JOYPAD_X, 0x1),
+    (RETRO_DEVICE_ID_JOYPAD_Y, 0x2),
+    (RETRO_DEVICE_ID_JOYPAD_L, 0x3),
+    (RETRO_DEVICE_ID_JOYPAD_R, 0xC),
+    (RETRO_DEVICE_ID_JOYPAD_L2, 0xD),
+    (RETRO_DEVICE_ID_JOYPAD_R2, 0xE),
+    (RETRO_DEVICE_ID_JOYPAD_L3, 0xA),
+    (RETRO_DEVICE_ID_JOYPAD_R3, 0xB),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, 0xF),
+    (RETRO_DEVICE_ID_JOYPAD_START, 0x0),
+];
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+struct Core {
+    // boxed so the memory handed to the frontend stays in place
+    emu: Box<Emulation>,
+    // what the rom database chose, for options set back to auto
+    rom_settings: (Platform, Quirks, u32),
+    filter: Filter,
+    palette: Palette,
+    ips: f64,
+    instructions: f64,
+    audio: Audio,
+    samples: Vec<f32>,
+    audio_frames: Vec<i16>,
+    video: Vec<u32>,
+    // RetroPad buttons and the hex keys they press, for each player
+    buttons: Vec<(u32, u32, usize)>,
+    // descriptions given to the frontend, kept alive for it
+    descriptions: Vec<CString>,
+}
+
+impl Core {
+    fn new(rom: &[u8]) -> Core {
+        let mut emu = Box::new(Emulation::new());
+        emu.load(rom);
+        let rom_settings = (emu.platform(), emu.quirks(), emu.ticks_per_frame());
+        let hints = emu.rom_info().map(|info| info.keys.clone()).unwrap_or_default();
+        let mut buttons = Vec::new();
+        for player in 0..PLAYERS {
+            for (button, name, default) in PAD_BUTTONS {
+                let name = if player == 0 {
+                    name.to_string()
+                } else {
+                    // player2Up, player2A...
+                    format!("player{}{}{}", player + 1, name[..1].to_uppercase(), &name[1..])
+                };
+                let key = hints.iter().find(|(hint, _)| *hint == name).map_or(default, |(_, key)| *key as usize);
+                buttons.push((player, button, key));
+            }
+        }
+        buttons.extend(EXTRA_BUTTONS.iter().map(|(button, key)| (0, *button, *key)));
+        let mut core = Core {
+            emu,
+            rom_settings,
+            filter: Filter::new(Default::default()),
+            palette: Palette::default(),
+            ips: 0.0,
+            instructions: 0.0,
+            audio: Audio::new(SAMPLE_RATE),
+            samples: vec![0.0; (SAMPLE_RATE as f64 / FPS) as usize],
+            audio_frames: Vec::new(),
+            video: Vec::new(),
+            buttons,
+            descriptions: Vec::new(),
+        };
+        core.filter.push(&core.emu);
+        core
+    }
+
+    fn apply_options(&mut self, options: Options) {
+        let (platform, quirks, ticks) = self.rom_settings;
+        match options.platform {
+            Some(platform) => self.emu.set_platform(platform),
+            None => {
+                self.emu.set_platform(platform);
+                self.emu.set_quirks(quirks);
+                self.emu.set_ticks_per_frame(ticks);
+            },
+        }
+        let mut quirks = self.emu.quirks();
+        for (name, enabled) in options.quirks {
+            quirks.set(name, enabled);
+        }
+        self.emu.set_quirks(quirks);
+        if let Some(ips) = options.ips {
+            self.emu.set_ticks_per_frame((ips / 60).max(1));
+        }
+        self.ips = options.ips.unwrap_or(self.emu.ticks_per_frame() * 60) as f64;
+        self.palette = options.palette.unwrap_or_else(|| Palette::for_rom(self.emu.rom_info()));
+        if self.filter.mode() != options.filter {
+            self.filter.set_mode(options.filter);
+        }
+    }
+
+    fn input_descriptors(&mut self) -> Vec<InputDescriptor> {
+        self.descriptions = self.buttons.iter().map(|(_, _, key)| CString::new(format!("Key {:X}", key)).unwrap()).collect();
+        self.buttons
+            .iter()
+            .zip(&self.descriptions)
+            .map(|((player, button, _), description)| InputDescriptor {
+                port: *player,
+                device: RETRO_DEVICE_JOYPAD,
+                index: 0,
+                id: *button,
+                description: description.as_ptr(),
+            })
+            .chain(std::iter::once(InputDescriptor { port: 0, device: 0, index: 0, id: 0, description: ptr::null() }))
+            .collect()
+    }
+
+    fn run_frame(&mut self, callbacks: &Callbacks) {
+        if let Some(input_state) = callbacks.input_state {
+            let mut keys = [false; 16];
+            for (player, button, key) in &self.buttons {
+                // SAFETY: a callback set by the frontend
+                keys[*key] |= unsafe { input_state(*player, RETRO_DEVICE_JOYPAD, 0, *button) } != 0;
+            }
+            for (key, pressed) in keys.iter().enumerate() {
+                self.emu.key_press(key, *pressed);
+            }
+        }
+
+        self.instructions += self.ips / FPS;
+        let ticks = self.instructions as u32;
+        self.instructions -= ticks as f64;
+        self.emu.run(ticks);
+        self.audio.fill(&self.emu, &mut self.samples);
+        self.emu.tick_timers();
+        self.filter.push(&self.emu);
+
+        self.video.clear();
+        self.video.extend(self.filter.pixels(&self.palette).map(|[r, g, b]| (r as u32) << 16 | (g as u32) << 8 | b as u32));
+        if let Some(video_refresh) = callbacks.video_refresh {
+            let (width, height) = (self.filter.width(), self.filter.height());
+            // SAFETY: the frame stays alive until the next run
+            unsafe { video_refresh(self.video.as_ptr() as *const c_void, width as u32, height as u32, width * 4) };
+        }
+
+        self.audio_frames.clear();
+        for sample in &self.samples {
+            let sample = (sample * i16::MAX as f32) as i16;
+            self.audio_frames.extend([sample, sample]);
+        }
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            let mut written = 0;
+            // frontends may take fewer frames than offered
+            while written < self.samples.len() {
+                // SAFETY: the frames are interleaved left and right samples
+                let count = unsafe { audio_sample_batch(self.audio_frames[written * 2..].as_ptr(), self.samples.len() - written) };
+                if count == 0 {
+                    break;
+                }
+                written += count;
+            }
+        }
+    }
+}
+
+fn environment(cmd: u32, data: *mut c_void) -> bool {
+    match CALLBACKS.lock().unwrap().environment {
+        // SAFETY: a callback set by the frontend, data matches cmd
+        Some(environment) => unsafe { environment(cmd, data) },
+        None => false,
+    }
+}
+
+// a cheat code is an address and a value in hex, e.g. "2F3 03" or "2F3:03"
+fn parse_cheat(code: &str) -> Option<(u16, u8)> {
+    let (address, value) = code.trim().split_once([' ', ':'])?;
+    let address = address.trim_start_matches("0x");
+    Some((u16::from_str_radix(address, 16).ok()?, u8::from_str_radix(value.trim(), 16).ok()?))
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> u32 {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    let Some(info) = info.as_mut() else {
+        return;
+    };
+    info.library_name = c"FeO8".as_ptr();
+    info.library_version = concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char;
+    info.valid_extensions = c"ch8|c8|sc8|xo8".as_ptr();
+    info.need_fullpath = false;
+    info.block_extract = false;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    let Some(info) = info.as_mut() else {
+        return;
+    };
+    info.geometry = GameGeometry {
+        base_width: feo_core::SCREEN_WIDTH as u32,
+        base_height: feo_core::SCREEN_HEIGHT as u32,
+        max_width: feo_core::HIRES_SCREEN_WIDTH as u32,
+        max_height: feo_core::HIRES_SCREEN_HEIGHT as u32,
+        aspect_ratio: 2.0,
+    };
+    info.timing = SystemTiming { fps: FPS, sample_rate: SAMPLE_RATE as f64 };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CALLBACKS.lock().unwrap().environment = Some(callback);
+    let mut variables = options::variables();
+    environment(RETRO_ENVIRONMENT_SET_VARIABLES, variables.as_mut_ptr() as *mut c_void);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+// the buzzer is played in batches only
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: u32, _device: u32) {}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() || game.size > feo_core::MAX_ROM_SIZE {
+        return false;
+    }
+    let rom = std::slice::from_raw_parts(game.data as *const u8, game.size);
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format as *mut u32 as *mut c_void) {
+        return false;
+    }
+    let mut core = Core::new(rom);
+    if let Some(callback) = CALLBACKS.lock().unwrap().environment {
+        core.apply_options(options::read(callback));
+    }
+    let mut descriptors = core.input_descriptors();
+    environment(RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS, descriptors.as_mut_ptr() as *mut c_void);
+    *CORE.lock().unwrap() = Some(core);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_game_type: u32, _info: *const GameInfo, _num_info: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> u32 {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        core.emu.soft_reset();
+        core.filter.clear();
+        core.filter.push(&core.emu);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let mut updated = false;
+    environment(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &mut updated as *mut bool as *mut c_void);
+    let callbacks = CALLBACKS.lock().unwrap();
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+    if updated {
+        if let Some(callback) = callbacks.environment {
+            core.apply_options(options::read(callback));
+        }
+    }
+    if let Some(input_poll) = callbacks.input_poll {
+        // SAFETY: a callback set by the frontend
+        unsafe { input_poll() };
+    }
+    core.run_frame(&callbacks);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    feo_core::STATE_SIZE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = CORE.lock().unwrap();
+    let Some(core) = core.as_ref() else {
+        return false;
+    };
+    let state = core.emu.save_state();
+    if data.is_null() || size < state.len() {
+        return false;
+    }
+    ptr::copy_nonoverlapping(state.as_ptr(), data as *mut u8, state.len());
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return false;
+    };
+    if data.is_null() {
+        return false;
+    }
+    let state = std::slice::from_raw_parts(data as *const u8, size.min(feo_core::STATE_SIZE));
+    core.emu.load_state(state).is_ok()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        core.emu.cheats_mut().clear();
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(index: u32, enabled: bool, code: *const c_char) {
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+    if code.is_null() {
+        return;
+    }
+    let code = CStr::from_ptr(code).to_string_lossy();
+    let Some((address, value)) = parse_cheat(&code) else {
+        return;
+    };
+    let cheats = core.emu.cheats_mut();
+    let index = index as usize;
+    if cheats.len() <= index {
+        cheats.resize(index + 1, Cheat { address: 0, value: 0, enabled: false, name: String::new() });
+    }
+    cheats[index] = Cheat { enabled, ..Cheat::new(address, value, "") };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: u32) -> *mut c_void {
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return ptr::null_mut();
+    };
+    match id {
+        RETRO_MEMORY_SAVE_RAM => core.emu.user_flags_mut().as_mut_ptr() as *mut c_void,
+        RETRO_MEMORY_SYSTEM_RAM => core.emu.memory_mut().as_mut_ptr() as *mut c_void,
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: u32) -> usize {
+    let core = CORE.lock().unwrap();
+    let Some(core) = core.as_ref() else {
+        return 0;
+    };
+    match id {
+        RETRO_MEMORY_SAVE_RAM => core.emu.user_flags().len(),
+        RETRO_MEMORY_SYSTEM_RAM => core.emu.memory().len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,87 @@
+/*
+    Core options, shown by the frontend in its menu.
+    Every option starts at auto, which uses what the rom database knows about the game,
+    or the defaults of its platform.
+*/
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use feo_core::filter::FilterMode;
+use feo_core::palette::Palette;
+use feo_core::{Platform, Quirks};
+
+use crate::ffi::{self, Variable};
+
+// key and "description; value|value|..." as libretro wants them, the first value is the default
+const VARIABLES: [(&CStr, &CStr); 11] = [
+    (c"feo8_platform", c"Platform; auto|originalChip8|modernChip8|chip48|superchip1|superchip|xochip"),
+    (c"feo8_speed", c"Instructions per second; auto|300|500|600|700|900|1000|1200|1500|2000|3000|5000|10000|30000"),
+    (c"feo8_palette", c"Palette; auto|feo8|classic|green|amber|contrast|octo"),
+    (c"feo8_filter", c"Flicker filter; off|blend|decay|or"),
+    (c"feo8_quirk_shift", c"Shift quirk (8XY6/8XYE shift VX); auto|enabled|disabled"),
+    (c"feo8_quirk_memoryIncrementByX", c"Memory quirk (FX55/FX65 add X to I); auto|enabled|disabled"),
+    (c"feo8_quirk_memoryLeaveIUnchanged", c"Memory quirk (FX55/FX65 leave I unchanged); auto|enabled|disabled"),
+    (c"feo8_quirk_wrap", c"Wrap quirk (sprites wrap around the screen); auto|enabled|disabled"),
+    (c"feo8_quirk_jump", c"Jump quirk (BNNN jumps to VX + NNN); auto|enabled|disabled"),
+    (c"feo8_quirk_vblank", c"Display wait quirk (drawing waits for the next frame); auto|enabled|disabled"),
+    (c"feo8_quirk_logic", c"Logic quirk (8XY1/8XY2/8XY3 reset VF); auto|enabled|disabled"),
+];
+
+// the options as chosen, none where they are left at auto
+#[derive(Default)]
+pub struct Options {
+    pub platform: Option<Platform>,
+    pub ips: Option<u32>,
+    pub palette: Option<Palette>,
+    pub filter: FilterMode,
+    pub quirks: Vec<(&'static str, bool)>,
+}
+
+// the null terminated list of options, to be passed with RETRO_ENVIRONMENT_SET_VARIABLES
+pub fn variables() -> Vec<Variable> {
+    VARIABLES
+        .iter()
+        .map(|(key, value)| Variable { key: key.as_ptr(), value: value.as_ptr() })
+        .chain(std::iter::once(Variable { key: ptr::null(), value: ptr::null() }))
+        .collect()
+}
+
+// reads the options through the environment callback
+pub fn read(environment: ffi::EnvironmentFn) -> Options {
+    let get = |key: &CStr| -> Option<String> {
+        let mut variable = Variable { key: key.as_ptr(), value: ptr::null::<c_char>() };
+        // SAFETY: the frontend fills in a string it keeps alive until the next call
+        unsafe {
+            let found = environment(ffi::RETRO_ENVIRONMENT_GET_VARIABLE, &mut variable as *mut Variable as *mut _);
+            if !found || variable.value.is_null() {
+                return None;
+            }
+            Some(CStr::from_ptr(variable.value).to_string_lossy().into_owned())
+        }
+    };
+    let mut options = Options::default();
+    for (key, _) in VARIABLES {
+        let Some(value) = get(key) else {
+            continue;
+        };
+        let name = key.to_str().unwrap_or_default().trim_start_matches("feo8_");
+        match name {
+            "platform" => options.platform = Platform::from_id(&value),
+            "speed" => options.ips = value.parse().ok(),
+            "palette" => options.palette = Palette::theme(&value),
+            "filter" => options.filter = FilterMode::parse(&value).unwrap_or_default(),
+            _ => {
+                let quirk = name.trim_start_matches("quirk_");
+                let Some(quirk) = Quirks::NAMES.iter().find(|known| **known == quirk) else {
+                    continue;
+                };
+                match value.as_str() {
+                    "enabled" => options.quirks.push((quirk, true)),
+                    "disabled" => options.quirks.push((quirk, false)),
+                    _ => (),
+                }
+            },
+        }
+    }
+    options
+}
@@ -0,0 +1,160 @@
+/*
+    A tiny libretro frontend for checking the core without RetroArch.
+    Loads the built library, runs a rom for a few seconds with the A button held now and then,
+    and checks that frames and audio come out and that a saved state plays back the same way.
+    Runs with cargo test. Cargo doesn't build the library for integration tests, so the test
+    builds it first and loads it from the folder above the test's own, e.g. target/debug.
+*/
+use std::ffi::{c_char, c_void};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+
+use libloading::{Library, Symbol};
+
+const ENVIRONMENT_SET_PIXEL_FORMAT: u32 = 10;
+const JOYPAD_A: u32 = 8;
+const FRAMES: usize = 300;
+
+#[repr(C)]
+struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+// what the core gave the frontend so far
+struct Output {
+    frame: usize,
+    video: Vec<u32>,
+    width: u32,
+    height: u32,
+    samples: usize,
+    loud: usize,
+}
+
+static OUTPUT: Mutex<Output> = Mutex::new(Output { frame: 0, video: Vec::new(), width: 0, height: 0, samples: 0, loud: 0 });
+
+unsafe extern "C" fn environment(cmd: u32, _data: *mut c_void) -> bool {
+    cmd == ENVIRONMENT_SET_PIXEL_FORMAT
+}
+
+unsafe extern "C" fn video_refresh(data: *const c_void, width: u32, height: u32, pitch: usize) {
+    let mut output = OUTPUT.lock().unwrap();
+    let pixels = std::slice::from_raw_parts(data as *const u32, pitch / 4 * height as usize);
+    output.video = pixels.to_vec();
+    output.width = width;
+    output.height = height;
+}
+
+unsafe extern "C" fn audio_sample_batch(data: *const i16, frames: usize) -> usize {
+    let mut output = OUTPUT.lock().unwrap();
+    let samples = std::slice::from_raw_parts(data, frames * 2);
+    output.samples += frames;
+    output.loud += samples.iter().filter(|sample| **sample != 0).count();
+    frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+// holds A for half a second every two seconds, so games get going
+unsafe extern "C" fn input_state(port: u32, _device: u32, _index: u32, id: u32) -> i16 {
+    let frame = OUTPUT.lock().unwrap().frame;
+    (port == 0 && id == JOYPAD_A && frame % 120 < 30) as i16
+}
+
+struct Core<'a> {
+    run: Symbol<'a, unsafe extern "C" fn()>,
+    serialize_size: Symbol<'a, unsafe extern "C" fn() -> usize>,
+    serialize: Symbol<'a, unsafe extern "C" fn(*mut c_void, usize) -> bool>,
+    unserialize: Symbol<'a, unsafe extern "C" fn(*const c_void, usize) -> bool>,
+}
+
+impl Core<'_> {
+    // runs frames and returns the last picture
+    fn run_frames(&self, count: usize) -> Vec<u32> {
+        for _ in 0..count {
+            unsafe { (self.run)() };
+            OUTPUT.lock().unwrap().frame += 1;
+        }
+        OUTPUT.lock().unwrap().video.clone()
+    }
+
+    fn save(&self) -> Vec<u8> {
+        let mut state = vec![0; unsafe { (self.serialize_size)() }];
+        if !unsafe { (self.serialize)(state.as_mut_ptr() as *mut c_void, state.len()) } {
+            panic!("retro_serialize failed");
+        }
+        state
+    }
+
+    fn load(&self, state: &[u8]) {
+        if !unsafe { (self.unserialize)(state.as_ptr() as *const c_void, state.len()) } {
+            panic!("retro_unserialize failed");
+        }
+    }
+}
+
+// builds the core with the profile of the test and returns where it went
+fn build_core() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo.args(["build", "--lib", "--manifest-path", concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")]);
+    if dir.ends_with("release") {
+        cargo.arg("--release");
+    }
+    assert!(cargo.status().unwrap().success(), "unable to build the core");
+    dir.join(libloading::library_filename("feo8_libretro"))
+}
+
+#[test]
+fn plays_a_rom_and_its_save_states() {
+    let rom = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../test/roms/BRIX")).unwrap();
+    let library_path = build_core();
+
+    unsafe {
+        let library = Library::new(&library_path).unwrap_or_else(|err| panic!("unable to load {}: {}", library_path.display(), err));
+        macro_rules! call {
+            ($name:literal, $type:ty $(, $arg:expr)*) => {{
+                let function: Symbol<$type> = library.get($name.as_bytes()).unwrap_or_else(|err| panic!("missing {}: {}", $name, err));
+                function($($arg),*)
+            }};
+        }
+
+        call!("retro_set_environment", unsafe extern "C" fn(unsafe extern "C" fn(u32, *mut c_void) -> bool), environment);
+        call!("retro_set_video_refresh", unsafe extern "C" fn(unsafe extern "C" fn(*const c_void, u32, u32, usize)), video_refresh);
+        call!("retro_set_audio_sample_batch", unsafe extern "C" fn(unsafe extern "C" fn(*const i16, usize) -> usize), audio_sample_batch);
+        call!("retro_set_input_poll", unsafe extern "C" fn(unsafe extern "C" fn()), input_poll);
+        call!("retro_set_input_state", unsafe extern "C" fn(unsafe extern "C" fn(u32, u32, u32, u32) -> i16), input_state);
+        call!("retro_init", unsafe extern "C" fn());
+
+        let game = GameInfo { path: std::ptr::null(), data: rom.as_ptr() as *const c_void, size: rom.len(), meta: std::ptr::null() };
+        assert!(call!("retro_load_game", unsafe extern "C" fn(*const GameInfo) -> bool, &game), "retro_load_game failed");
+
+        let core = Core {
+            run: library.get(b"retro_run").unwrap(),
+            serialize_size: library.get(b"retro_serialize_size").unwrap(),
+            serialize: library.get(b"retro_serialize").unwrap(),
+            unserialize: library.get(b"retro_unserialize").unwrap(),
+        };
+
+        core.run_frames(FRAMES);
+        let state = core.save();
+        let frame = OUTPUT.lock().unwrap().frame;
+        let played = core.run_frames(FRAMES);
+        OUTPUT.lock().unwrap().frame = frame;
+        core.load(&state);
+        let replayed = core.run_frames(FRAMES);
+        assert!(played == replayed, "the frames after loading the state differ");
+
+        let output = OUTPUT.lock().unwrap();
+        assert!(output.video.iter().any(|pixel| *pixel != output.video[0]), "the last frame is blank");
+        assert_eq!(output.samples, FRAMES * 3 * 800, "not all audio was played");
+        drop(output);
+
+        call!("retro_unload_game", unsafe extern "C" fn());
+        call!("retro_deinit", unsafe extern "C" fn());
+    }
+}
@@ -0,0 +1,72 @@
+/*
+    Maps keys to the hex keypad, laid out on the left of a QWERTY keyboard:
+        1 2 3 4        1 2 3 C
+        Q W E R        4 5 6 D
+        A S D F   ->   7 8 9 E
+        Z X C V        A 0 B F
+    Most terminals only report key presses, so a key counts as held for a moment after
+    each press, kept down by the terminal repeating the key while it is held.
+    Terminals with the kitty keyboard protocol report releases, and those are used instead.
+*/
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use feo_core::Emulation;
+
+const KEYS: [(char, usize); 16] = [
+    ('1', 0x1), ('2', 0x2), ('3', 0x3), ('4', 0xC),
+    ('q', 0x4), ('w', 0x5), ('e', 0x6), ('r', 0xD),
+    ('a', 0x7), ('s', 0x8), ('d', 0x9), ('f', 0xE),
+    ('z', 0xA), ('x', 0x0), ('c', 0xB), ('v', 0xF),
+];
+
+pub struct Keypad {
+    // when each key was last pressed, while it counts as held
+    held: [Option<Instant>; 16],
+    hold: Duration,
+    // whether the terminal reports releases
+    releases: bool,
+}
+
+impl Keypad {
+    pub fn new(hold: Duration, releases: bool) -> Self {
+        Keypad { held: [None; 16], hold, releases }
+    }
+
+    // returns false for keys that aren't on the keypad, and for shortcuts like Ctrl+C
+    pub fn handle(&mut self, emu: &mut Emulation, event: &KeyEvent) -> bool {
+        let KeyCode::Char(character) = event.code else {
+            return false;
+        };
+        if event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return false;
+        }
+        let Some((_, key)) = KEYS.iter().find(|(known, _)| *known == character.to_ascii_lowercase()) else {
+            return false;
+        };
+        match event.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                self.held[*key] = Some(Instant::now());
+                emu.key_press(*key, true);
+            },
+            KeyEventKind::Release => {
+                self.held[*key] = None;
+                emu.key_press(*key, false);
+            },
+        }
+        true
+    }
+
+    // lets go of keys that haven't been repeated in time, to be called every frame
+    pub fn update(&mut self, emu: &mut Emulation) {
+        if self.releases {
+            return;
+        }
+        for (key, pressed) in self.held.iter_mut().enumerate() {
+            if pressed.is_some_and(|time| time.elapsed() > self.hold) {
+                *pressed = None;
+                emu.key_press(key, false);
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+/*
+    Terminal frontend, for running roms on machines without a display, e.g. over SSH.
+    The display is drawn with half block or braille characters in true colour,
+    with a status line of the registers and frame rate below it.
+    Esc or Ctrl+C quits, P pauses and F2 restarts the program.
+*/
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Print, ResetColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use feo_core::filter::{Filter, FilterMode};
+use feo_core::palette::Palette;
+use feo_core::{Emulation, Platform};
+
+use keypad::Keypad;
+use render::{Mode, Renderer};
+
+mod keypad;
+mod render;
+
+const USAGE: &str = "\
+Usage: tui [OPTIONS] ROM
+
+Options:
+  --braille          draw 2x4 pixels per character instead of 1x2
+  --palette <NAME>   colour palette: feo8, classic, green, amber, contrast or octo
+  --filter <MODE>    flicker filter: off, blend, decay[:FRAMES] or or[:FRAMES]
+  --platform <ID>    originalChip8, modernChip8, chip48, superchip1, superchip or xochip
+  --ips <N>          instructions per second (default: from the rom database or platform)
+  --hold <MS>        how long a key stays down after it is pressed, for terminals
+                     that don't report releases (default 250)
+  -h, --help         show this message";
+
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// the status line is drawn every few frames, so it can be read and doesn't flood slow connections
+const STATUS_FRAMES: u32 = 6;
+
+struct Options {
+    rom: String,
+    mode: Mode,
+    palette: Option<Palette>,
+    filter: FilterMode,
+    platform: Option<Platform>,
+    ips: Option<u32>,
+    hold: Duration,
+}
+
+fn parse_options() -> Result<Option<Options>, String> {
+    let mut options = Options {
+        rom: String::new(),
+        mode: Mode::HalfBlock,
+        palette: None,
+        filter: FilterMode::Off,
+        platform: None,
+        ips: None,
+        hold: Duration::from_millis(250),
+    };
+    let number = |option: &str, value: String| value.parse::<u32>().ok().filter(|n| *n > 0).ok_or(format!("{} must be a whole number, got '{}'", option, value));
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--braille" => options.mode = Mode::Braille,
+            "--palette" => {
+                let name = value(&arg)?;
+                options.palette = Some(Palette::theme(&name).ok_or(format!("unknown palette '{}'", name))?);
+            },
+            "--filter" => {
+                let mode = value(&arg)?;
+                options.filter = FilterMode::parse(&mode).ok_or(format!("unknown filter '{}'", mode))?;
+            },
+            "--platform" => {
+                let id = value(&arg)?;
+                options.platform = Some(Platform::from_id(&id).ok_or(format!("unknown platform '{}'", id))?);
+            },
+            "--ips" => options.ips = Some(number(&arg, value(&arg)?)?),
+            "--hold" => options.hold = Duration::from_millis(number(&arg, value(&arg)?)? as u64),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if !options.rom.is_empty() => return Err(format!("unexpected argument '{}'", arg)),
+            _ => options.rom = arg,
+        }
+    }
+    if options.rom.is_empty() {
+        return Err(String::from("no rom given"));
+    }
+    Ok(Some(options))
+}
+
+// raw mode and the alternate screen, undone when dropped so the shell is usable again after a panic
+struct Terminal {
+    releases: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Terminal> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            execute!(io::stdout(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+        Ok(Terminal { releases })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(io::stdout(), ResetColor, LeaveAlternateScreen, Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(err) => {
+            eprintln!("Error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        },
+    };
+    let buffer = match fs::read(&options.rom) {
+        Ok(buffer) if buffer.len() <= feo_core::MAX_ROM_SIZE => buffer,
+        Ok(_) => {
+            eprintln!("{} is too large to be a rom", options.rom);
+            process::exit(1);
+        },
+        Err(err) => {
+            eprintln!("Unable to open {}: {}", options.rom, err);
+            process::exit(1);
+        },
+    };
+    let mut emu = Emulation::new();
+    emu.load(&buffer);
+    if let Some(platform) = options.platform {
+        emu.set_platform(platform);
+    }
+    if let Err(err) = run(&mut emu, &options) {
+        eprintln!("Terminal error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(emu: &mut Emulation, options: &Options) -> io::Result<()> {
+    let terminal = Terminal::enter()?;
+    let mut out = io::BufWriter::new(io::stdout());
+    let palette = options.palette.unwrap_or_else(|| Palette::for_rom(emu.rom_info()));
+    let ips = options.ips.unwrap_or(emu.ticks_per_frame() * 60) as f64;
+    let mut keypad = Keypad::new(options.hold, terminal.releases);
+    let mut renderer = Renderer::new(options.mode);
+    let mut filter = Filter::new(options.filter);
+    filter.push(emu);
+    let mut paused = false;
+    let mut instructions = 0.0;
+    let mut frame = 0;
+    let mut fps = 0.0;
+    let mut fps_start = (Instant::now(), 0);
+    let mut next_frame = Instant::now();
+
+    loop {
+        // input until the next frame is due
+        while event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+            match event::read()? {
+                Event::Key(key) => {
+                    if keypad.handle(emu, &key) || key.kind == KeyEventKind::Release {
+                        continue;
+                    }
+                    match key {
+                        KeyEvent { code: KeyCode::Esc, .. } => return Ok(()),
+                        KeyEvent { code: KeyCode::Char('c'), modifiers, .. } if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                        KeyEvent { code: KeyCode::Char('p' | 'P'), .. } => paused = !paused,
+                        KeyEvent { code: KeyCode::F(2), .. } => {
+                            emu.soft_reset();
+                            filter.clear();
+                            filter.push(emu);
+                        },
+                        _ => (),
+                    }
+                },
+                Event::Resize(..) => {
+                    queue!(out, ResetColor, Clear(ClearType::All))?;
+                    renderer.invalidate();
+                },
+                _ => (),
+            }
+        }
+        keypad.update(emu);
+
+        if !paused {
+            instructions += ips / 60.0;
+            let ticks = instructions as u32;
+            instructions -= ticks as f64;
+            emu.run(ticks);
+            emu.tick_timers();
+            filter.push(emu);
+        }
+        renderer.draw(&mut out, &filter, &palette)?;
+
+        frame += 1;
+        let elapsed = fps_start.0.elapsed().as_secs_f64();
+        if elapsed >= 1.0 {
+            fps = (frame - fps_start.1) as f64 / elapsed;
+            fps_start = (Instant::now(), frame);
+        }
+        if frame % STATUS_FRAMES == 0 {
+            let status = status(emu, fps, paused);
+            queue!(out, ResetColor, MoveTo(0, renderer.rows(&filter)), Print(status), Clear(ClearType::UntilNewLine))?;
+        }
+        out.flush()?;
+
+        // after a stall, carry on from now instead of racing to catch up
+        next_frame += FRAME_TIME;
+        if next_frame + FRAME_TIME * 6 < Instant::now() {
+            next_frame = Instant::now();
+        }
+    }
+}
+
+fn status(emu: &Emulation, fps: f64, paused: bool) -> String {
+    let registers: String = emu.registers().iter().map(|value| format!("{:02X}", value)).collect();
+    format!(
+        "PC {:04X} I {:04X} SP {:X} DT {:02X} ST {:02X} V {} {}",
+        emu.program_counter(),
+        emu.index_register(),
+        emu.stack_pointer(),
+        emu.delay_timer(),
+        emu.sound_timer(),
+        registers,
+        if paused { String::from("PAUSED") } else { format!("{:.0} FPS", fps) },
+    )
+}
@@ -0,0 +1,120 @@
+/*
+    Draws the display with text characters, in the colours of the palette.
+    Half blocks show two pixels above each other in a character, the top one in the
+    foreground colour of the character and the bottom one in its background colour.
+    Braille characters show 2x4 pixels in a character, taking a quarter of the rows,
+    but all lit pixels of a character share one colour.
+*/
+use std::io::{self, Write};
+
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::{Color, Print, SetBackgroundColor, SetForegroundColor};
+use feo_core::filter::Filter;
+use feo_core::palette::Palette;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    HalfBlock,
+    Braille,
+}
+
+// bit of each dot of a braille character, by row and column
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+pub struct Renderer {
+    mode: Mode,
+    // the pixels drawn last, a frame that doesn't change isn't drawn again
+    last: Vec<[u8; 3]>,
+    pixels: Vec<[u8; 3]>,
+    colors: (Option<Color>, Option<Color>),
+}
+
+fn color([r, g, b]: [u8; 3]) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+impl Renderer {
+    pub fn new(mode: Mode) -> Self {
+        Renderer { mode, last: Vec::new(), pixels: Vec::new(), colors: (None, None) }
+    }
+
+    // rows of text the display takes
+    pub fn rows(&self, filter: &Filter) -> u16 {
+        match self.mode {
+            Mode::HalfBlock => filter.height().div_ceil(2) as u16,
+            Mode::Braille => filter.height().div_ceil(4) as u16,
+        }
+    }
+
+    // makes the next frame be drawn, after the terminal was cleared
+    pub fn invalidate(&mut self) {
+        self.last.clear();
+    }
+
+    // queues the display when it changed, returns whether it did
+    pub fn draw(&mut self, out: &mut impl Write, filter: &Filter, palette: &Palette) -> io::Result<bool> {
+        self.pixels.clear();
+        self.pixels.extend(filter.pixels(palette));
+        if self.pixels.is_empty() || self.pixels == self.last {
+            return Ok(false);
+        }
+        // colours carry over between frames only in the terminal's memory, which is lost on a clear
+        self.colors = (None, None);
+        let (width, height) = (filter.width(), filter.height());
+        match self.mode {
+            Mode::HalfBlock => {
+                for (row, y) in (0..height).step_by(2).enumerate() {
+                    queue!(out, MoveTo(0, row as u16))?;
+                    for x in 0..width {
+                        let top = self.pixels[y * width + x];
+                        let bottom = self.pixels.get((y + 1) * width + x).copied().unwrap_or(top);
+                        if top == bottom {
+                            self.set_colors(out, self.colors.0.unwrap_or(color(top)), color(bottom))?;
+                            queue!(out, Print(' '))?;
+                        } else {
+                            self.set_colors(out, color(top), color(bottom))?;
+                            queue!(out, Print('▀'))?;
+                        }
+                    }
+                }
+            },
+            Mode::Braille => {
+                let background = palette.colors[0];
+                for (row, y) in (0..height).step_by(4).enumerate() {
+                    queue!(out, MoveTo(0, row as u16))?;
+                    for x in (0..width).step_by(2) {
+                        let mut dots = 0;
+                        let mut lit = None;
+                        for (dy, bits) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dx, bit) in bits.iter().enumerate() {
+                                let Some(pixel) = self.pixels.get((y + dy) * width + x + dx) else {
+                                    continue;
+                                };
+                                if x + dx < width && *pixel != background {
+                                    dots |= bit;
+                                    lit.get_or_insert(*pixel);
+                                }
+                            }
+                        }
+                        self.set_colors(out, color(lit.unwrap_or(background)), color(background))?;
+                        queue!(out, Print(char::from_u32(0x2800 + dots).unwrap_or(' ')))?;
+                    }
+                }
+            },
+        }
+        std::mem::swap(&mut self.last, &mut self.pixels);
+        Ok(true)
+    }
+
+    fn set_colors(&mut self, out: &mut impl Write, foreground: Color, background: Color) -> io::Result<()> {
+        if self.colors.0 != Some(foreground) {
+            queue!(out, SetForegroundColor(foreground))?;
+        }
+        if self.colors.1 != Some(background) {
+            queue!(out, SetBackgroundColor(background))?;
+        }
+        self.colors = (Some(foreground), Some(background));
+        Ok(())
+    }
+}
@@ -1,32 +1,135 @@
 use feo_core::*;
+use feo_core::audio::Audio;
+use feo_core::debug::Stop;
+use feo_core::disasm;
+use feo_core::filter::{Filter, FilterMode};
+use feo_core::palette::Palette;
 
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, KeyboardEvent};
-use js_sys::Uint8Array;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData, KeyboardEvent};
+use js_sys::{Function, Uint8Array};
 
 
 #[wasm_bindgen]
 pub struct EmulatorWasm {
     emu: Emulation,
-    ctx: CanvasRenderingContext2d,
+    // None for a headless emulator
+    ctx: Option<CanvasRenderingContext2d>,
+    filter: Filter,
+    palette: Palette,
+    // RGBA pixels of the last frame, at the resolution of the display
+    frame: Vec<u8>,
+    // KeyboardEvent.code values of each hex key
+    keymap: [Vec<String>; 16],
+    audio: Audio,
+    storage: Option<Storage>,
+    // the user flags as they were last stored, to notice when the program changes them
+    stored_flags: Vec<u8>,
+}
+
+// callbacks of the page that keep data between visits, e.g. in localStorage or IndexedDB:
+// load(key) returns a Uint8Array or null, save(key, data) stores one.
+// Kept as closures so tests can store in memory without JavaScript
+type LoadFn = Box<dyn Fn(&str) -> Result<Option<Vec<u8>>, JsValue>>;
+type SaveFn = Box<dyn Fn(&str, &[u8]) -> Result<(), JsValue>>;
+
+struct Storage {
+    load: LoadFn,
+    save: SaveFn,
+}
+
+impl Storage {
+    fn from_js(load: Function, save: Function) -> Storage {
+        Storage {
+            load: Box::new(move |key| {
+                let data = load.call1(&JsValue::NULL, &JsValue::from_str(key))?;
+                Ok(data.dyn_ref::<Uint8Array>().map(|data| data.to_vec()))
+            }),
+            save: Box::new(move |key, data| {
+                save.call2(&JsValue::NULL, &JsValue::from_str(key), &Uint8Array::from(data))?;
+                Ok(())
+            }),
+        }
+    }
+
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>, JsValue> {
+        (self.load)(key)
+    }
+
+    fn save(&self, key: &str, data: &[u8]) -> Result<(), JsValue> {
+        (self.save)(key, data)
+    }
+}
+
+// until the page tells the AudioContext's rate
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+// the keypad on the left of a QWERTY keyboard, by position so it works with any layout
+const DEFAULT_KEYMAP: [&str; 16] = [
+    "KeyX", "Digit1", "Digit2", "Digit3",
+    "KeyQ", "KeyW", "KeyE", "KeyA",
+    "KeyS", "KeyD", "KeyZ", "KeyC",
+    "Digit4", "KeyR", "KeyF", "KeyV",
+];
+
+// a disassembled instruction, size is 4 for XO-CHIP's F000 NNNN and 2 otherwise
+#[wasm_bindgen(getter_with_clone)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u16,
+    pub size: u16,
+    pub text: String,
+    pub breakpoint: bool,
 }
 
 // Bindings for JS
 #[wasm_bindgen]
 impl EmulatorWasm {
+    // draws into a canvas element, or the one with the given id
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<EmulatorWasm, JsValue>  {
+    pub fn new(canvas: JsValue) -> Result<EmulatorWasm, JsError> {
+        let canvas = match canvas.as_string() {
+            Some(id) => web_sys::window()
+                .and_then(|window| window.document())
+                .ok_or_else(|| JsError::new("no document to find the canvas in"))?
+                .get_element_by_id(&id)
+                .ok_or_else(|| JsError::new(&format!("no element with the id '{}'", id)))?
+                .dyn_into::<HtmlCanvasElement>()
+                .map_err(|_| JsError::new(&format!("the element '{}' is not a canvas", id)))?,
+            None => canvas
+                .dyn_into::<HtmlCanvasElement>()
+                .map_err(|_| JsError::new("expected a canvas element or its id"))?,
+        };
+        let ctx = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|ctx| ctx.dyn_into::<CanvasRenderingContext2d>().ok())
+            .ok_or_else(|| JsError::new("unable to get a 2d context from the canvas"))?;
+        let mut emulator = EmulatorWasm::headless();
+        emulator.ctx = Some(ctx);
+        Ok(emulator)
+    }
+
+    // an emulator without a display that doesn't need a DOM, e.g. for running tests under Node.
+    // The frame is still available from update_frame and frame_ptr
+    #[wasm_bindgen]
+    pub fn headless() -> EmulatorWasm {
         let emu = Emulation::new();
-        let document = web_sys::window().unwrap().document().unwrap();
-        let canvas = document.get_element_by_id("canvas").unwrap();
-        let canvas: HtmlCanvasElement = canvas
-            .dyn_into::<HtmlCanvasElement>()
-            .map_err(|_| ())
-            .unwrap();
-        let ctx = canvas.get_context("2d")?.unwrap()
-            .dyn_into::<CanvasRenderingContext2d>()?;
-        Ok(EmulatorWasm{emu, ctx})
+        let filter = Filter::new(FilterMode::Off);
+        let palette = Palette::theme("classic").unwrap();
+        EmulatorWasm {
+            emu,
+            ctx: None,
+            filter,
+            palette,
+            frame: Vec::new(),
+            keymap: default_keymap(),
+            audio: Audio::new(DEFAULT_SAMPLE_RATE),
+            storage: None,
+            stored_flags: Vec::new(),
+        }
     }
 
     #[wasm_bindgen]
@@ -37,64 +140,482 @@ impl EmulatorWasm {
     #[wasm_bindgen]
     pub fn tick_timers(&mut self) {
         self.emu.tick_timers();
+        self.filter.push(&self.emu);
+        // programs save high scores to the user flags, keep them as soon as they change
+        if self.emu.user_flags() != self.stored_flags {
+            self.stored_flags = self.emu.user_flags().to_vec();
+            if let Some(storage) = self.rom_storage() {
+                let _ = storage.save(&self.key("flags"), &self.stored_flags);
+            }
+        }
     }
 
+    // flicker filter: off, blend, decay[:frames] or or[:frames], returns false for unknown ones
+    #[wasm_bindgen]
+    pub fn set_filter(&mut self, mode: &str) -> bool {
+        match FilterMode::parse(mode) {
+            Some(mode) => {
+                self.filter.set_mode(mode);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // restarts the loaded rom
     #[wasm_bindgen]
     pub fn reset(&mut self) {
+        self.emu.soft_reset();
+        self.filter.clear();
+    }
+
+    // clears all of ram, a rom has to be loaded again afterwards
+    #[wasm_bindgen]
+    pub fn hard_reset(&mut self) {
         self.emu.reset();
+        self.filter.clear();
+    }
+
+    // presses the hex key mapped to the event's physical key, returns false for other keys
+    // so the page can leave those to the browser
+    #[wasm_bindgen]
+    pub fn key_press(&mut self, event: KeyboardEvent, pressed: bool) -> bool {
+        let code = event.code();
+        match self.keymap.iter().position(|codes| codes.contains(&code)) {
+            Some(key) => {
+                self.emu.key_press(key, pressed);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // presses a hex key directly, e.g. from an on-screen keypad
+    #[wasm_bindgen]
+    pub fn press_hex(&mut self, key: usize, pressed: bool) {
+        if key < self.keymap.len() {
+            self.emu.key_press(key, pressed);
+        }
     }
 
+    // sets the KeyboardEvent.code values that press a hex key, e.g. ["KeyW", "ArrowUp"].
+    // Returns false for keys above F
     #[wasm_bindgen]
-    pub fn key_press(&mut self, event: KeyboardEvent, pressed: bool) {
-        let key = event.key();
-        if let Some(key_code) = key_map(&key) {
-            self.emu.key_press(key_code, pressed);
+    pub fn set_key(&mut self, key: usize, codes: Vec<String>) -> bool {
+        match self.keymap.get_mut(key) {
+            Some(mapped) => {
+                *mapped = codes;
+                true
+            },
+            None => false,
         }
     }
 
     #[wasm_bindgen]
-    pub fn load(&mut self, rom: Uint8Array) {
-        self.emu.load(&rom.to_vec());
+    pub fn key_codes(&self, key: usize) -> Vec<String> {
+        self.keymap.get(key).cloned().unwrap_or_default()
     }
 
     #[wasm_bindgen]
-    pub fn render(&mut self, scale: usize) {
-        let display = self.emu.get_display();
-        for i in 0..(SCREEN_WIDTH * SCREEN_HEIGHT) {
-            if display[i] {
-                let x = i % SCREEN_WIDTH;
-                let y = i / SCREEN_WIDTH;
-                self.ctx.fill_rect(
-                    (x * scale) as f64,
-                    (y * scale) as f64,
-                    scale as f64,
-                    scale as f64
-                );
+    pub fn reset_keymap(&mut self) {
+        self.keymap = default_keymap();
+    }
+
+    // loads a rom, restoring its user flags and the session saved with save_session from storage.
+    // Returns whether a session was restored
+    #[wasm_bindgen]
+    pub fn load(&mut self, rom: Uint8Array) -> bool {
+        self.load_rom(&rom.to_vec())
+    }
+
+    // sets the page's storage callbacks, see Storage
+    #[wasm_bindgen]
+    pub fn set_storage(&mut self, load: Function, save: Function) {
+        self.storage = Some(Storage::from_js(load, save));
+    }
+
+    // stores a save state of the loaded rom in a numbered slot
+    #[wasm_bindgen]
+    pub fn save_slot(&self, slot: u32) -> Result<(), JsValue> {
+        self.storage()?.save(&self.key(&format!("slot{}", slot)), &self.emu.save_state())
+    }
+
+    // returns false if nothing was saved in the slot
+    #[wasm_bindgen]
+    pub fn load_slot(&mut self, slot: u32) -> Result<bool, JsValue> {
+        match self.storage()?.load(&self.key(&format!("slot{}", slot)))? {
+            Some(state) => self.load_state(&state).map(|_| true).map_err(JsValue::from),
+            None => Ok(false),
+        }
+    }
+
+    // stores where the program is, for the page to call when it is closed or hidden.
+    // Loading the rom again continues from there
+    #[wasm_bindgen]
+    pub fn save_session(&self) -> Result<(), JsValue> {
+        self.storage()?.save(&self.key("session"), &self.emu.save_state())
+    }
+
+    // instructions per frame for the loaded rom
+    #[wasm_bindgen]
+    pub fn ticks_per_frame(&self) -> u32 {
+        self.emu.ticks_per_frame()
+    }
+
+    // title of the loaded rom from the rom database
+    #[wasm_bindgen]
+    pub fn title(&self) -> Option<String> {
+        self.emu.rom_info().map(|info| info.title.clone())
+    }
+
+    // runs up to `ticks` instructions, stopping before one with a breakpoint.
+    // Returns the address of the breakpoint, running again continues from there
+    #[wasm_bindgen]
+    pub fn run(&mut self, ticks: u32) -> Option<u16> {
+        match self.emu.run(ticks) {
+            Some(Stop::Breakpoint(address)) => Some(address),
+            _ => None,
+        }
+    }
+
+    // what stopped the program when it ran into something it couldn't do, e.g. "invalid instruction E000".
+    // It stays stopped until the next reset
+    #[wasm_bindgen]
+    pub fn fault(&self) -> Option<String> {
+        self.emu.fault().map(|fault| fault.to_string())
+    }
+
+    // runs the next instruction, ignoring breakpoints
+    #[wasm_bindgen]
+    pub fn step(&mut self) {
+        self.emu.step();
+    }
+
+    #[wasm_bindgen]
+    pub fn program_counter(&self) -> u16 {
+        self.emu.program_counter()
+    }
+
+    #[wasm_bindgen]
+    pub fn index_register(&self) -> u16 {
+        self.emu.index_register()
+    }
+
+    // V0-VF
+    #[wasm_bindgen]
+    pub fn registers(&self) -> Vec<u8> {
+        self.emu.registers().to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn stack_pointer(&self) -> u16 {
+        self.emu.stack_pointer()
+    }
+
+    // the return addresses on the stack, the last one was pushed last
+    #[wasm_bindgen]
+    pub fn stack(&self) -> Vec<u16> {
+        self.emu.stack().to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn delay_timer(&self) -> u8 {
+        self.emu.delay_timer()
+    }
+
+    #[wasm_bindgen]
+    pub fn sound_timer(&self) -> u8 {
+        self.emu.sound_timer()
+    }
+
+    // 4kb, or 64kb for XO-CHIP
+    #[wasm_bindgen]
+    pub fn memory_size(&self) -> usize {
+        self.emu.memory_size()
+    }
+
+    // a copy of `length` bytes of ram from the address, cut off at the end of memory
+    #[wasm_bindgen]
+    pub fn read_memory(&self, address: usize, length: usize) -> Vec<u8> {
+        let end = address.saturating_add(length).min(self.emu.memory_size());
+        self.emu.memory()[address.min(end)..end].to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn save_state(&self) -> Vec<u8> {
+        self.emu.save_state()
+    }
+
+    // fails for states that aren't for the loaded rom
+    #[wasm_bindgen]
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), JsError> {
+        self.emu.load_state(state).map_err(|err| JsError::new(&err.to_string()))?;
+        self.filter.clear();
+        self.filter.push(&self.emu);
+        Ok(())
+    }
+
+    // disassembles count instructions around PC, starting `before` instructions earlier
+    #[wasm_bindgen]
+    pub fn disassemble(&self, before: usize, count: usize) -> Vec<Instruction> {
+        disasm::around(&self.emu, self.emu.program_counter(), before, count)
+            .into_iter()
+            .map(|line| Instruction {
+                address: line.address,
+                opcode: line.opcode,
+                size: line.size,
+                text: line.text,
+                breakpoint: self.emu.breakpoints().contains(&line.address),
+            })
+            .collect()
+    }
+
+    // adds a breakpoint at the address, or removes the one there; returns whether one is set now
+    #[wasm_bindgen]
+    pub fn toggle_breakpoint(&mut self, address: u16) -> bool {
+        self.emu.toggle_breakpoint(address)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_breakpoint(&mut self, address: u16, enabled: bool) {
+        self.emu.set_breakpoint(address, enabled);
+    }
+
+    #[wasm_bindgen]
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.emu.breakpoints().iter().copied().collect()
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_breakpoints(&mut self) {
+        for address in self.breakpoints() {
+            self.emu.set_breakpoint(address, false);
+        }
+    }
+
+    // rate of the samples made by fill_audio, usually the AudioContext's sampleRate
+    #[wasm_bindgen]
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let volume = self.audio.volume();
+        self.audio = Audio::new(sample_rate);
+        self.audio.set_volume(volume);
+    }
+
+    // 0 to 1
+    #[wasm_bindgen]
+    pub fn set_volume(&mut self, volume: f32) {
+        self.audio.set_volume(volume);
+    }
+
+    // fills the buffer with the sound of the emulation as it is now, a frame's worth is
+    // sampleRate / 60 samples, made after running its instructions and before tick_timers.
+    // The samples only depend on the program and its input, so they can be checked in tests
+    #[wasm_bindgen]
+    pub fn fill_audio(&mut self, samples: &mut [f32]) {
+        self.audio.fill(&self.emu, samples);
+    }
+
+    // colours are written as RRGGBB, optionally starting with #, returns false for invalid ones
+    #[wasm_bindgen]
+    pub fn set_foreground(&mut self, color: &str) -> bool {
+        parse_color(color).map(|color| self.palette.colors[1] = color).is_some()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_background(&mut self, color: &str) -> bool {
+        parse_color(color).map(|color| self.palette.colors[0] = color).is_some()
+    }
+
+    // writes the display after the flicker filter into the frame buffer
+    #[wasm_bindgen]
+    pub fn update_frame(&mut self) {
+        self.frame.clear();
+        for [r, g, b] in self.filter.pixels(&self.palette) {
+            self.frame.extend([r, g, b, 255]);
+        }
+    }
+
+    // the frame buffer in wasm memory, 4 bytes per pixel, for uploading it to WebGL without a copy.
+    // The buffer moves when the resolution changes, so ask again after each update.
+    #[wasm_bindgen]
+    pub fn frame_ptr(&self) -> *const u8 {
+        self.frame.as_ptr()
+    }
+
+    #[wasm_bindgen]
+    pub fn frame_len(&self) -> usize {
+        self.frame.len()
+    }
+
+    #[wasm_bindgen]
+    pub fn frame_width(&self) -> usize {
+        self.filter.width()
+    }
+
+    #[wasm_bindgen]
+    pub fn frame_height(&self) -> usize {
+        self.filter.height()
+    }
+
+    // draws the frame at the resolution of the display, the canvas is resized to match it
+    // so the page scales it with css (image-rendering: pixelated keeps the pixels sharp)
+    #[wasm_bindgen]
+    pub fn render(&mut self) -> Result<(), JsValue> {
+        let (width, height) = (self.filter.width() as u32, self.filter.height() as u32);
+        if width == 0 {
+            return Ok(());
+        }
+        if self.ctx.is_none() {
+            return Err(JsError::new("a headless emulator has no canvas to render to").into());
+        }
+        self.update_frame();
+        let ctx = self.ctx.as_ref().unwrap();
+        if let Some(canvas) = ctx.canvas() {
+            if canvas.width() != width || canvas.height() != height {
+                canvas.set_width(width);
+                canvas.set_height(height);
             }
         }
+        let image = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&self.frame), width, height)?;
+        ctx.put_image_data(&image, 0.0, 0.0)
+    }
+
+}
+
+impl EmulatorWasm {
+    fn load_rom(&mut self, rom: &[u8]) -> bool {
+        self.emu.load(rom);
+        // the flags of the last rom aren't this one's high scores
+        self.emu.user_flags_mut().fill(0);
+        self.stored_flags = self.emu.user_flags().to_vec();
+        // an empty rom has nothing stored
+        if self.emu.rom().is_empty() {
+            return false;
+        }
+        let Some(storage) = &self.storage else {
+            return false;
+        };
+        // whatever can't be read is left as it is, the rom just starts from scratch
+        if let Ok(Some(flags)) = storage.load(&self.key("flags")) {
+            let user_flags = self.emu.user_flags_mut();
+            let length = flags.len().min(user_flags.len());
+            user_flags[..length].copy_from_slice(&flags[..length]);
+        }
+        self.stored_flags = self.emu.user_flags().to_vec();
+        match storage.load(&self.key("session")) {
+            Ok(Some(state)) => self.load_state(&state).is_ok(),
+            _ => false,
+        }
+    }
+
+    // the storage for the loaded rom, there is nothing to keep without one
+    fn rom_storage(&self) -> Option<&Storage> {
+        self.storage.as_ref().filter(|_| !self.emu.rom().is_empty())
+    }
+
+    fn storage(&self) -> Result<&Storage, JsValue> {
+        if self.emu.rom().is_empty() {
+            return Err(JsError::new("no rom has been loaded").into());
+        }
+        self.storage.as_ref().ok_or_else(|| JsError::new("no storage has been set").into())
+    }
 
+    // storage key of the loaded rom's data
+    fn key(&self, name: &str) -> String {
+        format!("feo8/{}/{}", self.emu.rom_hash(), name)
     }
+}
 
+fn default_keymap() -> [Vec<String>; 16] {
+    DEFAULT_KEYMAP.map(|code| vec![code.to_string()])
 }
 
-fn key_map(key: &str) -> Option<usize> {
-    match key {
-        "1" => Some(0x1),
-        "2" => Some(0x2),
-        "3" => Some(0x3),
-        "4" => Some(0xC),
-        "q" => Some(0x4),
-        "w" => Some(0x5),
-        "e" => Some(0x6),
-        "r" => Some(0xD),
-        "a" => Some(0x7),
-        "s" => Some(0x8),
-        "d" => Some(0x9),
-        "f" => Some(0xE),
-        "z" => Some(0xA),
-        "x" => Some(0x0),
-        "c" => Some(0xB),
-        "v" => Some(0xF),
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    match u32::from_str_radix(hex, 16) {
+        Ok(color) if hex.len() == 6 => Some([(color >> 16) as u8, (color >> 8) as u8, color as u8]),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use feo_core::audio::DEFAULT_VOLUME;
+
+    use super::*;
+
+    type Stored = Rc<RefCell<HashMap<String, Vec<u8>>>>;
+
+    // storage in memory, shared with the test
+    fn memory_storage() -> (Storage, Stored) {
+        let stored = Rc::new(RefCell::new(HashMap::new()));
+        let (loads, saves) = (stored.clone(), stored.clone());
+        let storage = Storage {
+            load: Box::new(move |key| Ok(loads.borrow().get(key).cloned())),
+            save: Box::new(move |key, data| {
+                saves.borrow_mut().insert(key.to_string(), data.to_vec());
+                Ok(())
+            }),
+        };
+        (storage, stored)
+    }
+
+    #[test]
+    fn fills_audio_while_the_buzzer_sounds() {
+        let mut emulator = EmulatorWasm::headless();
+        // VF = 2, sound timer = VF
+        emulator.emu.load(&[0x6F, 0x02, 0xFF, 0x18]);
+        emulator.set_sample_rate(28160);
+        let mut samples = [1.0; 64];
+        emulator.fill_audio(&mut samples);
+        assert_eq!(samples, [0.0; 64]);
+
+        emulator.tick();
+        emulator.tick();
+        emulator.fill_audio(&mut samples);
+        // a 440 Hz square wave, 64 samples long
+        assert_eq!(samples[..32], [DEFAULT_VOLUME; 32]);
+        assert_eq!(samples[32..], [-DEFAULT_VOLUME; 32]);
+
+        emulator.set_volume(0.5);
+        emulator.fill_audio(&mut samples);
+        assert_eq!(samples[..32], [0.5; 32]);
+    }
+
+    #[test]
+    fn keeps_user_flags_and_slots_per_rom() {
+        let mut emulator = EmulatorWasm::headless();
+        let (storage, stored) = memory_storage();
+        emulator.storage = Some(storage);
+
+        // nothing is stored without a rom
+        emulator.emu.user_flags_mut()[0] = 1;
+        emulator.tick_timers();
+        assert!(stored.borrow().is_empty());
+
+        let first = [0x60, 0x01, 0x12, 0x02];
+        let second = [0x60, 0x02, 0x12, 0x02];
+        assert!(!emulator.load_rom(&first));
+        assert_eq!(emulator.emu.user_flags()[0], 0);
+        emulator.emu.user_flags_mut()[0] = 42;
+        emulator.tick_timers();
+        assert!(stored.borrow().contains_key(&emulator.key("flags")));
+
+        // the next rom starts with its own flags
+        assert!(!emulator.load_rom(&second));
+        assert_eq!(emulator.emu.user_flags()[0], 0);
+        assert!(!emulator.load_rom(&first));
+        assert_eq!(emulator.emu.user_flags()[0], 42);
+
+        emulator.tick();
+        emulator.save_slot(1).unwrap();
+        emulator.emu.soft_reset();
+        assert!(!emulator.load_slot(2).unwrap());
+        assert!(emulator.load_slot(1).unwrap());
+        assert_eq!(emulator.emu.registers()[0], 1);
+    }
+}
@@ -0,0 +1,41 @@
+// AudioWorklet playing the samples the emulator makes each frame, posted to it from the page.
+// When the page runs ahead the oldest samples are dropped to keep the sound in time
+const MAX_QUEUED = 4
+
+class FeO8Processor extends AudioWorkletProcessor {
+    constructor() {
+        super()
+        this.queue = []
+        this.position = 0
+        this.port.onmessage = (evt) => {
+            this.queue.push(evt.data)
+            if (this.queue.length > MAX_QUEUED) {
+                this.queue.shift()
+                this.position = 0
+            }
+        }
+    }
+
+    process(inputs, outputs) {
+        const output = outputs[0]
+        const channel = output[0]
+        for (let i = 0; i < channel.length; i++) {
+            const samples = this.queue[0]
+            if (!samples) {
+                channel[i] = 0
+                continue
+            }
+            channel[i] = samples[this.position++]
+            if (this.position >= samples.length) {
+                this.queue.shift()
+                this.position = 0
+            }
+        }
+        for (let c = 1; c < output.length; c++) {
+            output[c].set(channel)
+        }
+        return true
+    }
+}
+
+registerProcessor("feo8", FeO8Processor)
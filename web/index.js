@@ -1,29 +1,27 @@
 import init, * as wasm from "wasm.js"
 
-const WIDTH = 64
-const HEIGHT = 32
-const SCALE = 15
-const TICKS_PER_FRAME = 10
-
 let frame = 0
+let audio = null
 
-const canvas = document.getElementById("canvas")
-canvas.width = WIDTH * SCALE
-canvas.height = HEIGHT * SCALE
-const ctx = canvas.getContext("2d")
-ctx.fillStyle = "black"
-ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE)
 const input = document.getElementById("fileinput")
 
 async function run() {
     await init()
-    let emu = new wasm.EmulatorWasm()
+    let emu = new wasm.EmulatorWasm("canvas")
+    emu.set_foreground("ffffff")
+    emu.set_background("000000")
     document.addEventListener("keydown", function(evt) {
-        emu.key_press(evt, true)
+        if (emu.key_press(evt, true)) {
+            evt.preventDefault()
+        }
     })
     document.addEventListener("keyup", function(evt) {
-        emu.key_press(evt, false)
+        if (emu.key_press(evt, false)) {
+            evt.preventDefault()
+        }
     })
+    setupKeypad(emu)
+    setupStorage(emu)
     input.addEventListener("change", function(evt) {
         // Handle file loading
     }, false)
@@ -41,29 +39,104 @@ async function run() {
 
         // Load in game as Uint8Array, send to .wasm, start main loop
         let fr = new FileReader()
-        fr.onload = function(e) {
+        fr.onload = async function(e) {
+            await startAudio(emu)
             let buffer = fr.result
             const rom = new Uint8Array(buffer)
-            emu.reset()
-            emu.load(rom)
+            emu.hard_reset()
+            if (emu.load(rom)) {
+                console.log("Continuing where the last session ended")
+            }
+            document.title = "FeO8 - " + (emu.title() || file.name)
             mainloop(emu)
         }
         fr.readAsArrayBuffer(file)
     }, false)
 }
 
+// pointer events give every touch its own pointer, so several keys can be held at once
+function setupKeypad(emu) {
+    for (const button of document.querySelectorAll("#keypad button")) {
+        const key = parseInt(button.dataset.key, 16)
+        const press = function(evt, pressed) {
+            evt.preventDefault()
+            button.classList.toggle("pressed", pressed)
+            emu.press_hex(key, pressed)
+        }
+        button.addEventListener("pointerdown", function(evt) {
+            // touches are captured by the key they started on, without that sliding off wouldn't release it
+            button.releasePointerCapture(evt.pointerId)
+            press(evt, true)
+        })
+        button.addEventListener("pointerup", evt => press(evt, false))
+        button.addEventListener("pointercancel", evt => press(evt, false))
+        button.addEventListener("pointerleave", evt => press(evt, false))
+        button.addEventListener("contextmenu", evt => evt.preventDefault())
+    }
+}
+
+// save states and high scores are kept in localStorage, base64 encoded
+function setupStorage(emu) {
+    emu.set_storage(function(key) {
+        const data = localStorage.getItem(key)
+        return data === null ? null : Uint8Array.from(atob(data), c => c.charCodeAt(0))
+    }, function(key, data) {
+        let text = ""
+        for (const byte of data) {
+            text += String.fromCharCode(byte)
+        }
+        localStorage.setItem(key, btoa(text))
+    })
+    // the page can be closed at any time once hidden
+    document.addEventListener("visibilitychange", function() {
+        if (document.visibilityState === "hidden" && frame !== 0) {
+            emu.save_session()
+        }
+    })
+    const slot = document.getElementById("slot")
+    document.getElementById("save").addEventListener("click", function() {
+        try {
+            emu.save_slot(slot.value)
+        } catch (err) {
+            alert("Unable to save slot " + slot.value + ": " + err.message)
+        }
+    })
+    document.getElementById("load").addEventListener("click", function() {
+        try {
+            if (!emu.load_slot(slot.value)) {
+                alert("Nothing saved in slot " + slot.value)
+            }
+        } catch (err) {
+            alert("Unable to load slot " + slot.value + ": " + err.message)
+        }
+    })
+}
+
+// browsers only allow sound after the user did something, like picking a rom
+async function startAudio(emu) {
+    if (audio) {
+        return
+    }
+    const context = new AudioContext()
+    await context.audioWorklet.addModule("audio.js")
+    const node = new AudioWorkletNode(context, "feo8", { outputChannelCount: [2] })
+    node.connect(context.destination)
+    emu.set_sample_rate(context.sampleRate)
+    audio = { node, samples: new Float32Array(Math.round(context.sampleRate / 60)) }
+}
+
 function mainloop(emu) {
     // Only draw every few ticks
-    for (let i = 0; i < TICKS_PER_FRAME; i++) {
+    const ticks = emu.ticks_per_frame()
+    for (let i = 0; i < ticks; i++) {
         emu.tick()
     }
+    if (audio) {
+        emu.fill_audio(audio.samples)
+        audio.node.port.postMessage(audio.samples.slice())
+    }
     emu.tick_timers()
-    // Clear the canvas before drawing
-    ctx.fillStyle = "black"
-    ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE)
-    // Set the draw color back to white before we render our frame
-    ctx.fillStyle = "white"
-    emu.render(SCALE)
+    emu.render()
     frame = window.requestAnimationFrame(() => {
         mainloop(emu)
     })